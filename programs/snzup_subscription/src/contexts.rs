@@ -1,15 +1,26 @@
 use anchor_lang::prelude::*;
-use crate::internal::State;
+use crate::internal::{OwnerIndex, State};
 
 // Account setup for creating a new challenge
 #[derive(Accounts)]
-#[instruction(challenge_id: u64)]
+#[instruction(
+    challenge_id: u64,
+    fee: u64,
+    commission: u8,
+    treasury: Pubkey,
+    distribution_deadline: i64,
+    subscriber_capacity: u16,
+    winner_capacity: u16
+)]
 pub struct Initialize<'info> {
-    // Create new state account with specific size and seeds
+    // Create new state account with specific size and seeds.
+    // `init` is what rejects a duplicate challenge_id for the same owner: the system program
+    // refuses to create an address that is already in use ("already in use" in the logs).
+    // The program never closes a state account, so a challenge_id is used once per owner.
     #[account(
         init,                    // Create new account
         payer = owner,          // Owner pays for account creation
-        space = State::MAX_SIZE, // Set account size
+        space = State::space(subscriber_capacity as usize, winner_capacity as usize), // Sized to the requested capacity
         seeds = [b"state", owner.key().as_ref(), &challenge_id.to_le_bytes()], // Unique address
         bump                     // Add randomness for security
     )]
@@ -21,6 +32,79 @@ pub struct Initialize<'info> {
 
     // Solana system program (needed for account creation)
     pub system_program: Program<'info, System>,
+
+    // Optional per-owner index; when passed, the new challenge_id is appended to it
+    #[account(
+        mut,
+        seeds = [b"owner_index", owner.key().as_ref()],
+        bump = owner_index.bump
+    )]
+    pub owner_index: Option<Account<'info, OwnerIndex>>,
+}
+
+// Account setup for creating an owner's challenge index
+#[derive(Accounts)]
+pub struct InitOwnerIndex<'info> {
+    // One index per owner, starts empty and grows per challenge
+    #[account(
+        init,
+        payer = owner,
+        space = OwnerIndex::space(0),
+        seeds = [b"owner_index", owner.key().as_ref()],
+        bump
+    )]
+    pub owner_index: Account<'info, OwnerIndex>,
+
+    // Owner the index lists challenges for (pays rent)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // Solana system program (needed for account creation)
+    pub system_program: Program<'info, System>,
+}
+
+// Account setup for creating a challenge sized to an expected subscriber count
+#[derive(Accounts)]
+#[instruction(
+    challenge_id: u64,
+    fee: u64,
+    commission: u8,
+    treasury: Pubkey,
+    expected_subscribers: u16
+)]
+pub struct InitializeSized<'info> {
+    // Create new state account with exactly the expected subscriber slots
+    #[account(
+        init,                    // Create new account
+        payer = owner,          // Owner pays for account creation
+        space = State::space(expected_subscribers as usize, State::DEFAULT_WINNER_CAPACITY as usize), // Sized to the expected count
+        seeds = [b"state", owner.key().as_ref(), &challenge_id.to_le_bytes()], // Unique address
+        bump                     // Add randomness for security
+    )]
+    pub state: Account<'info, State>,
+
+    // The person creating the challenge 
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // Solana system program (needed for account creation)
+    pub system_program: Program<'info, System>,
+}
+
+// Account setup for upgrading a state account to the current layout
+#[derive(Accounts)]
+pub struct Migrate<'info> {
+    /// CHECK: old layouts can't be loaded as State; program owner, discriminator,
+    /// PDA seeds and primary owner are all verified in migrate()
+    #[account(mut)]
+    pub state: UncheckedAccount<'info>,
+
+    // Primary owner (pays rent for any extra space)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // Solana system program (needed for rent transfer)
+    pub system_program: Program<'info, System>,
 }
 
 // Account setup for someone joining a challenge
@@ -29,7 +113,7 @@ pub struct Subscribe<'info> {
     // Find existing challenge state account
     #[account(
         mut,                    // We will modify this account
-        seeds = [b"state", state.owner.as_ref(), &state.challenge_id.to_le_bytes()], // Find by owner + challenge ID
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
         bump = state.bump       // Use stored bump for security
     )]
     pub state: Account<'info, State>,
@@ -40,6 +124,73 @@ pub struct Subscribe<'info> {
 
     // Solana system program (needed for SOL transfers)
     pub system_program: Program<'info, System>,
+
+    /// CHECK: NFT-gated challenges only; layout and holder are checked in verify_collection_gate
+    pub gate_token_account: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: NFT-gated challenges only; PDA and collection are checked in verify_collection_gate
+    pub gate_metadata: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: coupon signups only; pinned to the instructions sysvar, read in verify_coupon
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+
+    /// CHECK: coupon signups only; PDA ("coupon", state, subscriber) is checked in claim_coupon
+    #[account(mut)]
+    pub coupon_record: Option<UncheckedAccount<'info>>,
+}
+
+// Account setup for paying the balance of an installment subscription
+#[derive(Accounts)]
+pub struct CompletePayment<'info> {
+    // Find existing challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump       // Use stored bump for security
+    )]
+    pub state: Account<'info, State>,
+
+    // The subscriber settling their balance
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+
+    // Solana system program (needed for SOL transfers)
+    pub system_program: Program<'info, System>,
+}
+
+// Account setup for joining several people at once (wallets in remaining_accounts)
+#[derive(Accounts)]
+pub struct SubscribeBatch<'info> {
+    // Find existing challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump       // Use stored bump for security
+    )]
+    pub state: Account<'info, State>,
+
+    // Solana system program (needed for SOL transfers)
+    pub system_program: Program<'info, System>,
+}
+
+// Account setup for a sponsor topping up the prize pool
+#[derive(Accounts)]
+pub struct SponsorDeposit<'info> {
+    // Find existing challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump       // Use stored bump for security
+    )]
+    pub state: Account<'info, State>,
+
+    // Anyone funding the pool
+    #[account(mut)]
+    pub sponsor: Signer<'info>,
+
+    // Solana system program (needed for SOL transfers)
+    pub system_program: Program<'info, System>,
 }
 
 // Account setup for sending prizes to winners
@@ -48,12 +199,12 @@ pub struct SendBonus<'info> {
     // Find challenge state account
     #[account(
         mut,                    // We will modify this account
-        seeds = [b"state", state.owner.as_ref(), &state.challenge_id.to_le_bytes()], // Find by owner + challenge ID
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
         bump = state.bump       // Use stored bump for security
     )]
     pub state: Account<'info, State>,
 
-    // Challenge owner (must sign to distribute prizes)
+    // Owner or distributor running the payout (only signs; no lamports move to or from it)
     pub owner: Signer<'info>,
 
     // Company wallet that receives commission
@@ -62,6 +213,10 @@ pub struct SendBonus<'info> {
 
     // Solana system program (needed for SOL transfers)
     pub system_program: Program<'info, System>,
+
+    /// CHECK: leftover_policy 1 only; pinned to the stored primary owner, whoever signs the payout
+    #[account(mut, address = state.owner @ crate::internal::ErrorCode::OnlyOwner)]
+    pub owner_wallet: Option<UncheckedAccount<'info>>,
 }
 
 // Account setup for giving money back to subscribers
@@ -70,7 +225,7 @@ pub struct RefundBatch<'info> {
     // Find challenge state account
     #[account(
         mut,                    // We will modify this account
-        seeds = [b"state", state.owner.as_ref(), &state.challenge_id.to_le_bytes()], // Find by owner + challenge ID
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
         bump = state.bump       // Use stored bump for security
     )]
     pub state: Account<'info, State>,
@@ -81,8 +236,147 @@ pub struct RefundBatch<'info> {
 
     // Solana system program (needed for SOL transfers)
     pub system_program: Program<'info, System>,
+
+    // Treasury wallet receiving any refund processing fee
+    #[account(mut, address = state.treasury @ crate::internal::ErrorCode::InvalidInput)]
+    pub treasury_wallet: SystemAccount<'info>,
 }
 
+// Account setup for refunding a subscriber to a recovery address
+#[derive(Accounts)]
+pub struct RefundTo<'info> {
+    // Find challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump       // Use stored bump for security
+    )]
+    pub state: Account<'info, State>,
+
+    // Only the primary owner can redirect a refund
+    #[account(constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner)]
+    pub owner: Signer<'info>,
+
+    // Recovery wallet receiving the refund (must be a regular system account)
+    #[account(mut)]
+    pub destination: SystemAccount<'info>,
+
+    // Treasury wallet receiving any refund processing fee
+    #[account(mut, address = state.treasury @ crate::internal::ErrorCode::InvalidInput)]
+    pub treasury_wallet: SystemAccount<'info>,
+
+    // Solana system program (needed for SOL transfers)
+    pub system_program: Program<'info, System>,
+}
+
+// Account setup for removing one subscriber and refunding them
+#[derive(Accounts)]
+#[instruction(subscriber: Pubkey)]
+pub struct CancelAndRefundOne<'info> {
+    // Find challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump,      // Use stored bump for security
+        constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner // Check owner permission
+    )]
+    pub state: Account<'info, State>,
+
+    // Must be the challenge owner
+    pub owner: Signer<'info>,
+
+    // The subscriber's own wallet, which gets the refund
+    #[account(mut, address = subscriber @ crate::internal::ErrorCode::InvalidSubscriberAddress)]
+    pub subscriber_wallet: SystemAccount<'info>,
+
+    // Treasury wallet receiving any refund processing fee
+    #[account(mut, address = state.treasury @ crate::internal::ErrorCode::InvalidInput)]
+    pub treasury_wallet: SystemAccount<'info>,
+
+    // Solana system program (needed for SOL transfers)
+    pub system_program: Program<'info, System>,
+}
+
+// Account setup for a subscriber withdrawing from a challenge on their own
+#[derive(Accounts)]
+pub struct SelfRefund<'info> {
+    // Find challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump       // Use stored bump for security
+    )]
+    pub state: Account<'info, State>,
+
+    // The subscriber leaving, who gets the refund
+    #[account(mut)]
+    pub subscriber: Signer<'info>,
+
+    // Treasury wallet receiving any refund processing fee
+    #[account(mut, address = state.treasury @ crate::internal::ErrorCode::InvalidInput)]
+    pub treasury_wallet: SystemAccount<'info>,
+
+    // Solana system program (needed for SOL transfers)
+    pub system_program: Program<'info, System>,
+}
+
+// Account setup for the deadline refund escape hatch (any subscriber can call)
+#[derive(Accounts)]
+pub struct ForceRefundAll<'info> {
+    // Find challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump       // Use stored bump for security
+    )]
+    pub state: Account<'info, State>,
+
+    // A current subscriber triggering the refund
+    pub caller: Signer<'info>,
+
+    // Solana system program (needed for SOL transfers)
+    pub system_program: Program<'info, System>,
+}
+
+// Account setup for returning sponsor deposits to treasury
+#[derive(Accounts)]
+pub struct ReclaimSponsorFunds<'info> {
+    // Find challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump,      // Use stored bump for security
+        constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner // Check owner permission
+    )]
+    pub state: Account<'info, State>,
+
+    // Must be the challenge owner
+    pub owner: Signer<'info>,
+
+    // Treasury wallet receiving the sponsor funds
+    #[account(mut)]
+    pub treasury_wallet: SystemAccount<'info>,
+}
+
+// Account setup for sweeping stray lamports to treasury
+#[derive(Accounts)]
+pub struct WithdrawExcess<'info> {
+    // Find challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump,      // Use stored bump for security
+        constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner // Check owner permission
+    )]
+    pub state: Account<'info, State>,
+
+    // Must be the challenge owner
+    pub owner: Signer<'info>,
+
+    // Treasury wallet receiving the excess
+    #[account(mut)]
+    pub treasury_wallet: SystemAccount<'info>,
+}
 
 // Account setup for functions only the owner can use
 #[derive(Accounts)]
@@ -90,7 +384,24 @@ pub struct OnlyOwner<'info> {
     // Find challenge state account
     #[account(
         mut,                    // We will modify this account
-        seeds = [b"state", state.owner.as_ref(), &state.challenge_id.to_le_bytes()], // Find by owner + challenge ID
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump,      // Use stored bump for security
+        constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner // Check owner permission
+    )]
+    pub state: Account<'info, State>,
+
+    // Must be the challenge owner
+    pub owner: Signer<'info>,
+}
+
+// Account setup for recording the token mint
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct ConfigureToken<'info> {
+    // Find challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
         bump = state.bump,      // Use stored bump for security
         constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner // Check owner permission
     )]
@@ -98,6 +409,100 @@ pub struct OnlyOwner<'info> {
 
     // Must be the challenge owner
     pub owner: Signer<'info>,
+
+    /// CHECK: owner program and layout are checked in read_mint_decimals
+    #[account(address = mint)]
+    pub mint_account: UncheckedAccount<'info>,
+}
+
+// Account setup for adding subscriber slots (owner pays the extra rent)
+#[derive(Accounts)]
+pub struct GrowCapacity<'info> {
+    // Find challenge state account; grow_capacity() checks the size step and reallocs it
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump,      // Use stored bump for security
+        constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner // Only owner can grow
+    )]
+    pub state: Account<'info, State>,
+
+    // Challenge owner (pays for the extra space)
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    // Solana system program (needed for rent transfer)
+    pub system_program: Program<'info, System>,
+}
+
+// Account setup for permissionless state transitions (anyone can crank)
+#[derive(Accounts)]
+pub struct Crank<'info> {
+    // Find challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump       // Use stored bump for security
+    )]
+    pub state: Account<'info, State>,
+}
+
+// Account setup for read-only views anyone can call
+#[derive(Accounts)]
+pub struct ReadState<'info> {
+    // Find challenge state account (not modified)
+    #[account(
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump       // Use stored bump for security
+    )]
+    pub state: Account<'info, State>,
+}
+
+// Account setup for rotating the treasury wallet
+#[derive(Accounts)]
+#[instruction(new_treasury: Pubkey)]
+pub struct SetTreasury<'info> {
+    // Find challenge state account
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump,      // Use stored bump for security
+        constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner // Check owner permission
+    )]
+    pub state: Account<'info, State>,
+
+    // Must be the challenge owner
+    pub owner: Signer<'info>,
+
+    // New treasury; SystemAccount rejects PDAs and other program-owned accounts
+    #[account(address = new_treasury)]
+    pub treasury_wallet: SystemAccount<'info>,
+}
+
+// Account setup for moving leftover pool into a sibling challenge of the same creator
+#[derive(Accounts)]
+#[instruction(next_state: Pubkey)]
+pub struct RolloverTo<'info> {
+    // Source challenge; only its owner can roll funds out
+    #[account(
+        mut,                    // We will modify this account
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
+        bump = state.bump,      // Use stored bump for security
+        constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner // Check owner permission
+    )]
+    pub state: Account<'info, State>,
+
+    // Must be the challenge owner
+    pub owner: Signer<'info>,
+
+    // Destination: the same creator's seeds prove it is a sibling challenge
+    #[account(
+        mut,
+        address = next_state,
+        seeds = [b"state", state.creator.as_ref(), &next_state_account.challenge_id.to_le_bytes()],
+        bump = next_state_account.bump
+    )]
+    pub next_state_account: Account<'info, State>,
 }
 
 // Account setup for changing the subscription fee
@@ -106,7 +511,7 @@ pub struct UpdateFee<'info> {
     // Find challenge state account
     #[account(
         mut,                    // We will modify this account
-        seeds = [b"state", state.owner.as_ref(), &state.challenge_id.to_le_bytes()], // Find it by owner + challenge ID
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find it by creator + challenge ID
         bump = state.bump,      // Use stored bump for security
         constraint = owner.key() == state.owner @ crate::internal::ErrorCode::OnlyOwner // Only owner can change fee
     )]
@@ -122,7 +527,7 @@ pub struct UpdateCommission<'info> {
     // Find challenge state account
     #[account(
         mut,                    // We will modify this account
-        seeds = [b"state", state.owner.as_ref(), &state.challenge_id.to_le_bytes()], // Find by owner + challenge ID
+        seeds = [b"state", state.creator.as_ref(), &state.challenge_id.to_le_bytes()], // Find by creator + challenge ID
         bump = state.bump       // Use stored bump for security
     )]
     pub state: Account<'info, State>,
@@ -1,25 +1,316 @@
 // internal.rs
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash;
+use anchor_lang::solana_program::{ed25519_program, sysvar::instructions as ix_sysvar};
 
 
-// Bonus math 
+// Result of the bonus math, named so callers can't mix up the order
+#[derive(Clone, Copy)]
+pub struct BonusBreakdown {
+    pub commission_amount: u64, // company cut
+    pub bonus_pool: u64,        // what is left for winners
+    pub winner_amount: u64,     // equal share per winner
+}
+
+// Bonus math (checked: totals may be attacker-influenced, so fail with LamportMathError, never panic)
+// Commission is total_pool * commission_num / commission_den
 pub fn calculate_competition_bonus(
     total_pool: u64,
-    commission_rate: u8,
+    commission_num: u64,
+    commission_den: u64,
     winners_count: usize,
-) -> Result<(u64, u64, u64)> {
-    let commission_amount = (total_pool * commission_rate as u64) / 100;
-    let bonus_pool = total_pool - commission_amount;
-    let winner_amount = if winners_count > 0 { bonus_pool / winners_count as u64 } else { 0 };
-    Ok((commission_amount, bonus_pool, winner_amount))
+    round_up: bool,
+) -> Result<BonusBreakdown> {
+    let mut commission_amount = (total_pool as u128)
+        .checked_mul(commission_num as u128)
+        .and_then(|v| v.checked_div(commission_den as u128))
+        .and_then(|v| u64::try_from(v).ok())
+        .ok_or(error!(ErrorCode::LamportMathError))?;
+    let mut bonus_pool = total_pool
+        .checked_sub(commission_amount)
+        .ok_or(error!(ErrorCode::LamportMathError))?;
+    // No winners means nothing per winner
+    let mut winner_amount = bonus_pool.checked_div(winners_count as u64).unwrap_or(0);
+    // Round-up policy: winners get the ceiling share and commission absorbs the shortfall.
+    // Capped at total_pool / winners so commission + winners * share never exceeds total_pool.
+    if round_up && winners_count > 0 {
+        let n = winners_count as u64;
+        let ceil = bonus_pool.div_ceil(n).min(total_pool / n);
+        if ceil > winner_amount {
+            winner_amount = ceil;
+            let winners_total = winner_amount * n; // <= total_pool by the cap above
+            commission_amount = commission_amount.min(total_pool - winners_total);
+            bonus_pool = total_pool - commission_amount;
+        }
+    }
+    Ok(BonusBreakdown {
+        commission_amount,
+        bonus_pool,
+        winner_amount,
+    })
+}
+
+// Commission cut in basis points (10000 = 100%); u128 so large pools can't overflow
+pub fn commission_from_bps(amount: u64, commission_bps: u16) -> u64 {
+    (amount as u128 * commission_bps as u128 / State::BPS_DENOMINATOR as u128) as u64
+}
+
+// Price after a coupon discount in basis points (0 = full price, 10000 = free)
+pub fn apply_discount_bps(price: u64, discount_bps: u16) -> u64 {
+    price.saturating_sub(commission_from_bps(price, discount_bps))
+}
+
+// Fee guardrail: fee must sit in [min_fee, max_fee]; a zero bound means unbounded on that side
+pub fn validate_fee_bounds(fee: u64, min_fee: u64, max_fee: u64) -> Result<()> {
+    require!(min_fee == 0 || fee >= min_fee, ErrorCode::InvalidAmount);
+    require!(max_fee == 0 || fee <= max_fee, ErrorCode::InvalidAmount);
+    Ok(())
+}
+
+// CLOSED(2) and CANCELED(3) can't be left once reached
+pub fn is_terminal_status(status: u8) -> bool {
+    status == 2 || status == 3
+}
+
+// What coupon_authority signs: state || subscriber || discount_bps (u16 LE) || challenge_id (u64 LE).
+// The state key ties a coupon to one challenge account; challenge ids alone repeat across creators.
+pub fn coupon_message(state: &Pubkey, subscriber: &Pubkey, discount_bps: u16, challenge_id: u64) -> Vec<u8> {
+    let mut message = Vec::with_capacity(32 + 32 + 2 + 8);
+    message.extend_from_slice(state.as_ref());
+    message.extend_from_slice(subscriber.as_ref());
+    message.extend_from_slice(&discount_bps.to_le_bytes());
+    message.extend_from_slice(&challenge_id.to_le_bytes());
+    message
+}
+
+// Coupon check by instruction introspection: the instruction right before this one must be an
+// Ed25519 program call verifying one signature by `authority` over exactly `message`. The
+// precompile already rejected a bad signature; this makes sure it checked the data we expect.
+pub fn verify_coupon(
+    instructions: &AccountInfo,
+    authority: &Pubkey,
+    signature: &[u8; 64],
+    message: &[u8],
+) -> Result<()> {
+    let current = ix_sysvar::load_current_index_checked(instructions)? as usize;
+    require!(current > 0, ErrorCode::InvalidCoupon);
+    let ix = ix_sysvar::load_instruction_at_checked(current - 1, instructions)?;
+    require!(ix.program_id == ed25519_program::ID, ErrorCode::InvalidCoupon);
+
+    // num_signatures | padding | sig_off, sig_ix, pk_off, pk_ix, msg_off, msg_len, msg_ix (u16 LE)
+    let data = &ix.data;
+    require!(data.len() >= 16 && data[0] == 1, ErrorCode::InvalidCoupon);
+    let field = |i: usize| u16::from_le_bytes([data[2 + 2 * i], data[3 + 2 * i]]);
+    // u16::MAX = "in this instruction"; anything else could point the precompile elsewhere
+    require!(
+        field(1) == u16::MAX && field(3) == u16::MAX && field(6) == u16::MAX,
+        ErrorCode::InvalidCoupon
+    );
+    let slice = |off: u16, len: usize| {
+        data.get(off as usize..off as usize + len)
+            .ok_or(error!(ErrorCode::InvalidCoupon))
+    };
+    require!(slice(field(2), 32)? == authority.as_ref(), ErrorCode::InvalidCoupon);
+    require!(slice(field(0), 64)? == signature.as_ref(), ErrorCode::InvalidCoupon);
+    require!(slice(field(4), field(5) as usize)? == message, ErrorCode::InvalidCoupon);
+    Ok(())
 }
 
-// Subscription guardrails
-pub fn validate_subscription(state: &State, subscriber: &Pubkey) -> Result<()> {
+// A chunked payout that started but hasn't finished owns the balance: no money moves in or out
+// through other paths until the last chunk lands. After an emergency stop refunds take over.
+pub fn require_no_chunked_payout(state: &State) -> Result<()> {
+    require!(
+        state.paid || state.paid_winners.is_empty() || state.emergency_stopped,
+        ErrorCode::ChunkedPayoutInProgress
+    );
+    Ok(())
+}
+
+// Subscription guardrails; `now` is the caller's clock read so the window check is pure
+pub fn validate_subscription(state: &State, subscriber: &Pubkey, now: i64) -> Result<()> {
+    require_not_stopped(state)?;
     require!(state.status == 0, ErrorCode::ChallengeInProgressOrExpired); // Only Pending
-    require!(state.subscribers.len() < State::MAX_SUBSCRIBERS, ErrorCode::MaxSubscribersReached);
-    require!(!state.subscribers.contains(subscriber), ErrorCode::AlreadySubscribed);
+    // Signup window, when configured (0 = open-ended on that side)
+    if state.subscribe_start != 0 || state.subscribe_end != 0 {
+        require!(now >= state.subscribe_start, ErrorCode::SignupWindowNotOpen);
+        require!(
+            state.subscribe_end == 0 || now <= state.subscribe_end,
+            ErrorCode::ChallengeInProgressOrExpired
+        );
+    }
+    require!(state.subscribers.len() < state.subscriber_capacity as usize, ErrorCode::MaxSubscribersReached);
+    require!(!state.is_subscriber(subscriber), ErrorCode::AlreadySubscribed);
+    // Opt-in: organizers can't enter their own challenge
+    if state.owners_cannot_subscribe {
+        require!(
+            *subscriber != state.owner && !state.owners.contains(subscriber),
+            ErrorCode::OwnerCannotSubscribe
+        );
+    }
+    Ok(())
+}
+
+// Invite-only check. Leaf = sha256(subscriber); each step hashes the sorted pair
+// sha256(min(a, b) || max(a, b)), so proofs need no left/right flags. Zero root = open.
+pub fn verify_allow_list(state: &State, subscriber: &Pubkey, proof: &[[u8; 32]]) -> Result<()> {
+    if state.merkle_root == [0u8; 32] {
+        return Ok(());
+    }
+    let mut node = hash::hash(subscriber.as_ref()).to_bytes();
+    for sibling in proof {
+        node = if node <= *sibling {
+            hash::hashv(&[&node, sibling]).to_bytes()
+        } else {
+            hash::hashv(&[sibling, &node]).to_bytes()
+        };
+    }
+    require!(node == state.merkle_root, ErrorCode::NotOnAllowList);
+    Ok(())
+}
+
+// Version-sensitive instructions refuse to run against an older layout. Reads the version byte
+// straight after the discriminator rather than the loaded State, so the tag on the account is
+// what counts, not whatever the struct was filled with.
+pub fn require_current_version(state_ai: &AccountInfo) -> Result<()> {
+    let data = state_ai.try_borrow_data()?;
+    require!(data.len() > 8, ErrorCode::InvalidInput);
+    require!(data[8] == State::CURRENT_VERSION, ErrorCode::NeedsMigration);
+    Ok(())
+}
+
+// Single source of "now" for handlers. Helpers that check windows, deadlines and ramps take
+// `now` as an argument instead of reading the clock, so handlers read it once and pass it down.
+pub fn current_timestamp() -> Result<i64> {
+    Ok(current_clock()?.unix_timestamp)
+}
+
+// The one Clock sysvar read; callers that also need the slot take the whole Clock from here
+pub fn current_clock() -> Result<Clock> {
+    Ok(Clock::get()?)
+}
+
+// SPL mint layout: mint_authority (36) | supply (8) | decimals (1) | is_initialized (1) | ...
+const SPL_TOKEN_PROGRAM_ID: Pubkey = anchor_lang::solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+const MINT_BASE_LEN: usize = 82;
+const MINT_DECIMALS_OFFSET: usize = 44;
+
+// Decimals of an initialized SPL Token mint
+pub fn read_mint_decimals(mint: &AccountInfo) -> Result<u8> {
+    require!(*mint.owner == SPL_TOKEN_PROGRAM_ID, ErrorCode::InvalidMint);
+    let data = mint.try_borrow_data()?;
+    require!(data.len() >= MINT_BASE_LEN, ErrorCode::InvalidMint);
+    require!(data[MINT_DECIMALS_OFFSET + 1] == 1, ErrorCode::InvalidMint);
+    Ok(data[MINT_DECIMALS_OFFSET])
+}
+
+// SPL token account layout: mint (32) | owner (32) | amount (8) | ...
+const TOKEN_ACCOUNT_LEN: usize = 165;
+// Metaplex Token Metadata program; metadata PDA seeds ["metadata", program, mint]
+const METADATA_PROGRAM_ID: Pubkey = anchor_lang::solana_program::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+// First byte of a Metaplex metadata account (Key::MetadataV1); the program owns other account kinds too
+const METADATA_KEY_V1: u8 = 4;
+
+// Next n bytes of account data, or GateNotSatisfied if it runs short
+fn take<'a>(data: &'a [u8], off: &mut usize, n: usize) -> Result<&'a [u8]> {
+    let end = off.checked_add(n).ok_or(error!(ErrorCode::GateNotSatisfied))?;
+    let out = data.get(*off..end).ok_or(error!(ErrorCode::GateNotSatisfied))?;
+    *off = end;
+    Ok(out)
+}
+
+// Skip a borsh String (u32 length + bytes)
+fn skip_string(data: &[u8], off: &mut usize) -> Result<()> {
+    let len = u32::from_le_bytes(take(data, off, 4)?.try_into().unwrap()) as usize;
+    take(data, off, len)?;
+    Ok(())
+}
+
+// Collection gate: subscriber holds a token whose Metaplex metadata names gate_collection as
+// its verified collection. Parsed by hand (anchor_spl and the Metaplex crate aren't dependencies).
+pub fn verify_collection_gate(
+    state: &State,
+    subscriber: &Pubkey,
+    token_account: Option<&AccountInfo>,
+    metadata: Option<&AccountInfo>,
+) -> Result<()> {
+    let collection = match state.gate_collection {
+        Some(c) => c,
+        None => return Ok(()),
+    };
+    let (token_ai, metadata_ai) = match (token_account, metadata) {
+        (Some(t), Some(m)) => (t, m),
+        _ => return err!(ErrorCode::GateNotSatisfied),
+    };
+
+    // Token account: SPL-owned, held by the subscriber, non-zero balance
+    require!(*token_ai.owner == SPL_TOKEN_PROGRAM_ID, ErrorCode::GateNotSatisfied);
+    let token = token_ai.try_borrow_data()?;
+    require!(token.len() >= TOKEN_ACCOUNT_LEN, ErrorCode::GateNotSatisfied);
+    let mint = Pubkey::try_from(&token[0..32]).unwrap();
+    require!(token[32..64] == subscriber.to_bytes(), ErrorCode::GateNotSatisfied);
+    require!(
+        u64::from_le_bytes(token[64..72].try_into().unwrap()) >= 1,
+        ErrorCode::GateNotSatisfied
+    );
+
+    // Metadata must be the canonical PDA for that mint
+    require!(*metadata_ai.owner == METADATA_PROGRAM_ID, ErrorCode::GateNotSatisfied);
+    let (expected, _) = Pubkey::find_program_address(
+        &[b"metadata", METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &METADATA_PROGRAM_ID,
+    );
+    require!(*metadata_ai.key == expected, ErrorCode::GateNotSatisfied);
+
+    // key | update_authority | mint | name | symbol | uri | seller_fee_bps | creators |
+    // primary_sale_happened | is_mutable | edition_nonce | token_standard | collection
+    let data = metadata_ai.try_borrow_data()?;
+    require!(data.len() > 1 + 32 + 32 && data[0] == METADATA_KEY_V1, ErrorCode::GateNotSatisfied);
+    let mut off = 1 + 32 + 32;
+    skip_string(&data, &mut off)?;
+    skip_string(&data, &mut off)?;
+    skip_string(&data, &mut off)?;
+    take(&data, &mut off, 2)?;
+    if take(&data, &mut off, 1)?[0] == 1 {
+        let count = u32::from_le_bytes(take(&data, &mut off, 4)?.try_into().unwrap()) as usize;
+        take(&data, &mut off, count.saturating_mul(34))?; // address + verified + share
+    }
+    take(&data, &mut off, 2)?;
+    for _ in 0..2 {
+        // edition_nonce, token_standard: Option<u8>
+        if take(&data, &mut off, 1)?[0] == 1 {
+            take(&data, &mut off, 1)?;
+        }
+    }
+    require!(take(&data, &mut off, 1)?[0] == 1, ErrorCode::GateNotSatisfied);
+    let verified = take(&data, &mut off, 1)?[0] == 1;
+    let key = Pubkey::try_from(take(&data, &mut off, 32)?).unwrap();
+    require!(verified && key == collection, ErrorCode::GateNotSatisfied);
+    Ok(())
+}
+
+// After emergency_stop only refunds are allowed
+pub fn require_not_stopped(state: &State) -> Result<()> {
+    require!(!state.emergency_stopped, ErrorCode::ChallengeStopped);
+    Ok(())
+}
+
+// TVL cap: balance after an incoming transfer must stay within max_tvl (0 = unlimited)
+pub fn validate_tvl(state: &State, balance: u64, incoming: u64) -> Result<()> {
+    if state.max_tvl != 0 {
+        let after = balance
+            .checked_add(incoming)
+            .ok_or(error!(ErrorCode::LamportMathError))?;
+        require!(after <= state.max_tvl, ErrorCode::TvlCapExceeded);
+    }
+    Ok(())
+}
+
+// Treasury guardrails: commission and prizes must not flow to the same wallet
+pub fn validate_treasury(state: &State, treasury: &Pubkey) -> Result<()> {
+    require!(*treasury != Pubkey::default(), ErrorCode::InvalidInput);
+    require!(!state.is_subscriber(treasury), ErrorCode::InvalidInput);
+    require!(!state.winners_list.contains(treasury), ErrorCode::InvalidInput);
     Ok(())
 }
 
@@ -27,11 +318,32 @@ pub fn validate_subscription(state: &State, subscriber: &Pubkey) -> Result<()> {
 pub fn validate_primary_owner(signer: &Pubkey, expected_owner: &Pubkey) -> Result<()> {
     ErrorCode::validate_primary_owner(signer, expected_owner)
 }
-pub fn validate_allowed_user(signer: &Pubkey, state: &State) -> Result<()> {
-    ErrorCode::validate_allowed_user(signer, state)
+pub fn validate_distributor(signer: &Pubkey, state: &State) -> Result<()> {
+    ErrorCode::validate_distributor(signer, state)
+}
+
+
+
+// One commission recipient and its share of the commission
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct TreasurySplit {
+    pub wallet: Pubkey, // 32
+    pub bps: u16,       // 2  (share of the commission, all entries sum to 10000)
 }
 
+// One subscriber and what they actually paid (fees can vary with the ramp)
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub struct Subscription {
+    pub who: Pubkey,     // 32
+    pub paid: u64,       // 8  (lamports transferred at subscribe, refunded from this)
+    pub joined_at: i64,  // 8  (unix ts, 0 = migrated from v1)
+    pub referrer: Pubkey, // 32 (who invited them, default = no referral)
+    pub fully_paid: bool, // 1  (false while only an installment deposit is in)
+}
 
+impl Subscription {
+    pub const SIZE: usize = 32 + 8 + 8 + 32 + 1;
+}
 
 #[account]
 pub struct State {
@@ -39,25 +351,92 @@ pub struct State {
     pub bump: u8,                  // 1
     pub challenge_id: u64,         // 8
     pub fee: u64,                  // 8  (lamports)
-    pub commission: u8,            // 1  (0..=100)
+    pub commission: u8,            // 1  (0..=100, whole percent kept for parity; math uses commission_num/den)
     pub status: u8,                // 1  (0=PENDING,1=IN_PROGRESS,2=CLOSED,3=CANCELED)
     pub owner: Pubkey,             // 32
     pub treasury: Pubkey,          // 32 (pinned payout target)
     pub paid: bool,                // 1  (once true, distribution cannot run again)
-    pub op_counter: u64,           // 8  (operation counter for parity with Solidity)
+    pub op_counter: u64,           // 8  (operation counter for parity with Solidity, saturating)
     pub owners: Vec<Pubkey>,       // 4 + N*32
-    pub subscribers: Vec<Pubkey>,  // 4 + M*32
+    pub subscribers: Vec<Subscription>, // 4 + M*81 (kept sorted by who for binary search)
     pub winners_list: Vec<Pubkey>, // 4 + W*32
+    pub distribution_deadline: i64, // 8  (unix ts, 0 = no forced refund)
+    pub subscriber_capacity: u16,  // 2  (slots the account was sized for)
+    pub distribution_nonce: u64,   // 8  (must match on send_bonus_to_winners, +1 on success)
+    pub paid_winners: Vec<Pubkey>, // 4 + W*32 (winners already paid by send_bonus_chunk)
+    pub bonus_per_winner: u64,     // 8  (split fixed by the first chunk)
+    pub commission_bps: u16,       // 2  (0..=10000)
+    pub metadata_hash: [u8; 32],   // 32 (hash of the off-chain rules document)
+    pub sponsored_total: u64,      // 8  (lamports added by sponsors, not refundable)
+    pub treasuries: Vec<TreasurySplit>, // 4 + T*34 (empty = all commission to treasury)
+    pub winners_must_be_subscribers: bool, // 1 (strict mode for set_winners_list)
+    pub min_fee: u64,              // 8  (0 = no floor)
+    pub max_fee: u64,              // 8  (0 = no cap)
+    pub subscribe_start: i64,      // 8  (unix ts, 0 = open immediately)
+    pub subscribe_end: i64,        // 8  (unix ts, 0 = no end)
+    pub min_subscribers: u16,      // 2  (below this at subscribe_end the challenge cancels)
+    pub refund_fee: u64,           // 8  (kept per admin refund, 0..=fee)
+    pub randomness_commitment: [u8; 32], // 32 (sha256 of the reveal seed, zeros = none)
+    pub category: u8,              // 1  (app-defined: 0=fitness, 1=learning, ...)
+    pub owners_cannot_subscribe: bool, // 1 (opt-in: owners can't join their own challenge)
+    pub total_collected: u64,      // 8  (lifetime subscription fees, never decremented)
+    pub allow_program_winners: bool, // 1 (opt-in: winners may be PDAs / program-owned vaults)
+    pub winner_capacity: u16,      // 2  (winner slots this account was sized for)
+    pub in_distribution: bool,     // 1  (guard around the payout loop)
+    pub creator: Pubkey,           // 32 (PDA seed; owner can change, this never does)
+    pub ramp_base_fee: u64,        // 8  (fee at/before ramp_start)
+    pub ramp_max_fee: u64,         // 8  (fee at/after ramp_end)
+    pub ramp_start: i64,           // 8  (unix ts)
+    pub ramp_end: i64,             // 8  (unix ts, 0 = no ramp, flat fee)
+    pub max_tvl: u64,              // 8  (cap on the PDA balance in lamports, 0 = unlimited)
+    pub distribution_frozen: bool, // 1  (dispute hold on payouts only)
+    pub leftover_policy: u8,       // 1  (0=treasury, 1=owner, 2=leave in account)
+    pub merkle_root: [u8; 32],     // 32 (invite-only allow-list root, zeros = open)
+    pub unclaimed_deadline: i64,   // 8  (after this a CANCELED challenge can be swept, 0 = never)
+    pub approved_commission_recipients: Vec<Pubkey>, // 4 + R*32 (allowed per-payout treasury overrides)
+    pub emergency_stopped: bool,   // 1  (irreversible kill switch)
+    pub commission_num: u64,       // 8  (commission = available * num / den)
+    pub commission_den: u64,       // 8  (0 = not set, fall back to commission_bps)
+    pub mint: Pubkey,              // 32 (display currency, default = native SOL)
+    pub decimals: u8,              // 1  (mint decimals, 9 for native SOL)
+    pub winners_lock_status: u8,   // 1  (winners may be set once status >= this, 0 = any time)
+    pub event_seq: u64,            // 8  (seq of the last emitting instruction, for gap detection)
+    pub winner_amounts: Vec<u64>,  // 4 + W*8 (fixed prize per winner, parallel to winners_list; empty = equal split)
+    pub max_winner_ratio_bps: u16, // 2  (winners <= subscribers * ratio / 10000, 0 = no cap)
+    pub distributors: Vec<Pubkey>, // 4 + D*32 (may run payouts, nothing else)
+    pub total_refunded: u64,       // 8  (lifetime refunds out of subscriber fees)
+    pub total_distributed: u64,    // 8  (lifetime lamports sent out by payouts)
+    pub gate_collection: Option<Pubkey>, // 1 + 32 (only holders of this NFT collection may join)
+    pub installment_deadline: i64, // 8  (unix ts the balance is due by, 0 = no installments)
+    pub round_winners_up: bool,    // 1  (winners get the ceiling share, commission absorbs it)
+    pub allow_owner_winners: bool, // 1  (opt-in: owner/owners may be winners)
+    pub coupon_authority: Option<Pubkey>, // 1 + 32 (signs discount coupons, None = no coupons)
+    pub refund_window_secs: i64,   // 8  (individual refunds only this long after joining, 0 = always)
+    pub canceled_at: i64,          // 8  (unix ts status became CANCELED, 0 = not canceled)
+    pub chunk_leftover: u64,       // 8  (leftover fixed by the first chunk, paid by the last)
+    pub legacy_paid: bool,         // 1  (paid before migration, when payouts weren't counted)
 }
 
 impl State {
-    pub const CURRENT_VERSION: u8 = 1;
+    // 1 = fixed 100-subscriber layout (StateV1), 2 = StateV1 plus distribution_deadline
+    // (StateV2), 3 = current. Bump this whenever the State layout changes and add a migrate() arm.
+    pub const CURRENT_VERSION: u8 = 3;
 
-    pub const MAX_SUBSCRIBERS: usize = 100;
-    pub const MAX_WINNERS: usize = 10;
+    pub const MAX_SUBSCRIBERS: usize = 1000; // hard ceiling for subscriber_capacity
+    pub const MAX_WINNERS: usize = 100;      // hard ceiling for winner_capacity
+    pub const DEFAULT_WINNER_CAPACITY: u16 = 10;
     pub const MAX_OWNERS: usize = 5;
+    pub const MAX_TREASURIES: usize = 4;
+    pub const MAX_APPROVED_RECIPIENTS: usize = 4;
+    pub const MAX_DISTRIBUTORS: usize = 5;
+    pub const NATIVE_DECIMALS: u8 = 9;            // lamports per SOL = 10^9
+    pub const MAX_PAGE_SIZE: usize = 25;     // subscribers per SubscribersPage event
+    pub const BPS_DENOMINATOR: u16 = 10_000; // 100% in basis points
+    pub const MAX_CATEGORY: u8 = 15;         // highest category the app defines
+    pub const MIN_UNCLAIMED_GRACE_SECS: i64 = 30 * 24 * 60 * 60; // refunds stay open this long after cancel
 
-    pub const MAX_SIZE: usize =
+    // Account size for a given number of subscriber and winner slots
+    pub const fn space(subscriber_capacity: usize, winner_capacity: usize) -> usize {
         8 + // discriminator
         1 + // version
         1 + // bump
@@ -70,11 +449,434 @@ impl State {
         1 + // paid
         8 + // op_counter
         (4 + Self::MAX_OWNERS * 32) +
-        (4 + Self::MAX_SUBSCRIBERS * 32) +
-        (4 + Self::MAX_WINNERS * 32);
+        (4 + subscriber_capacity * Subscription::SIZE) +
+        (4 + winner_capacity * 32) +
+        8 + // distribution_deadline
+        2 + // subscriber_capacity
+        8 + // distribution_nonce
+        (4 + winner_capacity * 32) + // paid_winners
+        8 + // bonus_per_winner
+        2 + // commission_bps
+        32 + // metadata_hash
+        8 + // sponsored_total
+        (4 + Self::MAX_TREASURIES * 34) + // treasuries
+        1 + // winners_must_be_subscribers
+        8 + // min_fee
+        8 + // max_fee
+        8 + // subscribe_start
+        8 + // subscribe_end
+        2 + // min_subscribers
+        8 + // refund_fee
+        32 + // randomness_commitment
+        1 + // category
+        1 + // owners_cannot_subscribe
+        8 + // total_collected
+        1 + // allow_program_winners
+        2 + // winner_capacity
+        1 + // in_distribution
+        32 + // creator
+        8 + // ramp_base_fee
+        8 + // ramp_max_fee
+        8 + // ramp_start
+        8 + // ramp_end
+        8 + // max_tvl
+        1 + // distribution_frozen
+        1 + // leftover_policy
+        32 + // merkle_root
+        8 + // unclaimed_deadline
+        (4 + Self::MAX_APPROVED_RECIPIENTS * 32) + // approved_commission_recipients
+        1 + // emergency_stopped
+        8 + // commission_num
+        8 + // commission_den
+        32 + // mint
+        1 + // decimals
+        1 + // winners_lock_status
+        8 + // event_seq
+        (4 + winner_capacity * 8) + // winner_amounts
+        2 + // max_winner_ratio_bps
+        (4 + Self::MAX_DISTRIBUTORS * 32) + // distributors
+        8 + // total_refunded
+        8 + // total_distributed
+        (1 + 32) + // gate_collection
+        8 + // installment_deadline
+        1 + // round_winners_up
+        1 + // allow_owner_winners
+        (1 + 32) + // coupon_authority
+        8 + // refund_window_secs
+        8 + // canceled_at
+        8 + // chunk_leftover
+        1 // legacy_paid
+    }
+
+    // Subscribers are kept sorted so membership is a binary search, not a full scan;
+    // this keeps subscribe cost roughly flat as the list grows
+    pub fn is_subscriber(&self, who: &Pubkey) -> bool {
+        self.subscriber_index(who).is_ok()
+    }
+
+    pub fn subscriber_index(&self, who: &Pubkey) -> std::result::Result<usize, usize> {
+        self.subscribers.binary_search_by_key(who, |e| e.who)
+    }
+
+    // What this subscriber paid, if they are one
+    pub fn paid_by(&self, who: &Pubkey) -> Option<u64> {
+        self.subscriber_index(who).ok().map(|i| self.subscribers[i].paid)
+    }
+
+    // Sum of everything current subscribers paid in
+    pub fn total_paid(&self) -> Result<u64> {
+        self.subscribers
+            .iter()
+            .try_fold(0u64, |acc, e| acc.checked_add(e.paid))
+            .ok_or(error!(ErrorCode::LamportMathError))
+    }
+
+    // Insert at the sorted position (no-op if already present)
+    pub fn insert_subscriber(
+        &mut self,
+        who: Pubkey,
+        paid: u64,
+        joined_at: i64,
+        referrer: Pubkey,
+        fully_paid: bool,
+    ) {
+        if let Err(i) = self.subscriber_index(&who) {
+            self.subscribers
+                .insert(i, Subscription { who, paid, joined_at, referrer, fully_paid });
+        }
+    }
+
+    // Canonical winner order: ascending pubkey bytes, with winner_amounts permuted in step.
+    // Payouts walk winners_list in this order and remaining_accounts must follow it, so the
+    // mapping is the same however the list was built. Frozen once a chunked payout has started.
+    pub fn canonicalize_winners(&mut self) {
+        if !self.paid_winners.is_empty() {
+            return;
+        }
+        if self.winner_amounts.len() == self.winners_list.len() && !self.winner_amounts.is_empty() {
+            let mut pairs: Vec<(Pubkey, u64)> = self
+                .winners_list
+                .iter()
+                .copied()
+                .zip(self.winner_amounts.iter().copied())
+                .collect();
+            pairs.sort_by_key(|(w, _)| w.to_bytes());
+            self.winners_list = pairs.iter().map(|(w, _)| *w).collect();
+            self.winner_amounts = pairs.iter().map(|(_, a)| *a).collect();
+        } else {
+            self.winners_list.sort_by_key(|w| w.to_bytes());
+        }
+    }
+
+    // Subscribed and settled in full; only these can be selected as winners
+    pub fn is_active_subscriber(&self, who: &Pubkey) -> bool {
+        self.subscriber_index(who).is_ok_and(|i| self.subscribers[i].fully_paid)
+    }
+
+    // Fee a subscriber joining at `now` pays: flat fee, or linear from base to max across the ramp
+    pub fn current_fee(&self, now: i64) -> u64 {
+        if self.ramp_end == 0 {
+            return self.fee;
+        }
+        if now <= self.ramp_start {
+            return self.ramp_base_fee;
+        }
+        if now >= self.ramp_end {
+            return self.ramp_max_fee;
+        }
+        let elapsed = (now - self.ramp_start) as u128;
+        let span = (self.ramp_end - self.ramp_start) as u128;
+        let extra = (self.ramp_max_fee - self.ramp_base_fee) as u128 * elapsed / span;
+        self.ramp_base_fee + extra as u64
+    }
+
+    // Balance refunds may draw from: everything above rent except sponsor deposits
+    pub fn refundable_balance(&self, total: u64, rent_exempt: u64) -> u64 {
+        total
+            .saturating_sub(rent_exempt)
+            .saturating_sub(self.sponsored_total)
+    }
+
+    // Accounts created before commission_bps existed only carry the whole percent;
+    // read those as percent * 100 so old challenges keep their rate
+    pub fn effective_commission_bps(&self) -> u16 {
+        if self.commission_bps == 0 && self.commission != 0 {
+            self.commission as u16 * 100
+        } else {
+            self.commission_bps
+        }
+    }
+
+    // op_counter policy: saturating, everywhere. It is a display/parity counter that no check
+    // reads, so pinning at u64::MAX is harmless, while checked_add would let a full counter brick
+    // every admin instruction. All increments go through here so the policy stays uniform.
+    pub fn bump_op_counter(&mut self, n: u64) {
+        self.op_counter = self.op_counter.saturating_add(n);
+    }
+
+    // Every event carries seq. It goes up by exactly one per state-changing instruction, silent
+    // setters included, so indexers can tell from a gap that the account moved; all events of one
+    // instruction share it, views report the current value, and a failed or replayed-and-rejected
+    // transaction reverts it with everything else.
+    pub fn next_event_seq(&mut self) -> u64 {
+        self.event_seq = self.event_seq.saturating_add(1);
+        self.event_seq
+    }
+
+    // Accounting invariant: refunds so far + this one + payouts never exceed what came in.
+    // Payouts also spend sponsor deposits, so those count on the income side; otherwise a
+    // sponsored payout would eat the refund budget subscribers' own fees back.
+    pub fn check_refund_budget(&self, amount: u64) -> Result<()> {
+        let spent = self
+            .total_refunded
+            .checked_add(amount)
+            .and_then(|v| v.checked_add(self.total_distributed))
+            .ok_or(error!(ErrorCode::LamportMathError))?;
+        let income = self
+            .total_collected
+            .checked_add(self.sponsored_total)
+            .ok_or(error!(ErrorCode::LamportMathError))?;
+        require!(spent <= income, ErrorCode::InsufficientContractBalance);
+        Ok(())
+    }
+
+    // Every move to CANCELED goes through here so the unclaimed grace period has a start time
+    pub fn mark_canceled(&mut self, now: i64) {
+        if self.status != 3 {
+            self.status = 3; // 3 = CANCELED
+            self.canceled_at = now;
+        }
+    }
+
+    // Earliest unclaimed deadline a sweep may act on: the grace period after cancel
+    pub fn sweep_allowed_deadline(&self, deadline: i64) -> bool {
+        self.canceled_at != 0
+            && deadline >= self.canceled_at.saturating_add(Self::MIN_UNCLAIMED_GRACE_SECS)
+    }
+
+    // Individual refunds close refund_window_secs after joining. Migrated subscribers have no
+    // join time (0) and stay refundable, as does everyone when the window is 0.
+    pub fn check_refund_window(&self, joined_at: i64, now: i64) -> Result<()> {
+        if self.refund_window_secs == 0 || joined_at == 0 {
+            return Ok(());
+        }
+        require!(
+            now.saturating_sub(joined_at) <= self.refund_window_secs,
+            ErrorCode::RefundWindowClosed
+        );
+        Ok(())
+    }
+
+    // Rate the payout math uses, as (num, den)
+    pub fn commission_fraction(&self) -> (u64, u64) {
+        if self.commission_den == 0 {
+            (self.effective_commission_bps() as u64, Self::BPS_DENOMINATOR as u64)
+        } else {
+            (self.commission_num, self.commission_den)
+        }
+    }
+
+    // Keep all representations in step
+    pub fn set_commission_bps(&mut self, commission_bps: u16) {
+        self.commission_bps = commission_bps;
+        self.commission = (commission_bps / 100) as u8;
+        self.commission_num = commission_bps as u64;
+        self.commission_den = Self::BPS_DENOMINATOR as u64;
+    }
+
+    // Exact fraction; bps and percent keep the rounded-down rate for display.
+    // Caller validates num <= den && den > 0.
+    pub fn set_commission_fraction(&mut self, num: u64, den: u64) {
+        let bps = (num as u128 * Self::BPS_DENOMINATOR as u128 / den as u128) as u16;
+        self.set_commission_bps(bps);
+        self.commission_num = num;
+        self.commission_den = den;
+    }
+
+    // Whole pool to treasury, however the rate is written
+    pub fn is_full_commission(&self) -> bool {
+        let (num, den) = self.commission_fraction();
+        num == den
+    }
+}
+
+// Challenge ids created by one owner, so clients can enumerate them from a single account.
+// Seeds: ["owner_index", owner]
+#[account]
+pub struct OwnerIndex {
+    pub owner: Pubkey,             // 32
+    pub bump: u8,                  // 1
+    pub challenge_ids: Vec<u64>,   // 4 + N*8 (in creation order, grows by realloc)
+}
+
+impl OwnerIndex {
+    pub const MAX_CHALLENGES: usize = 1000;
+
+    pub fn space(len: usize) -> usize {
+        8 + // discriminator
+        32 + // owner
+        1 + // bump
+        (4 + len * 8) // challenge_ids
+    }
+}
+
+// Layout written by version 1 of the program (fixed 100-subscriber account).
+// Only used by migrate() to read accounts that can't be loaded as State yet.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct StateV1 {
+    pub version: u8,
+    pub bump: u8,
+    pub challenge_id: u64,
+    pub fee: u64,
+    pub commission: u8,
+    pub status: u8,
+    pub owner: Pubkey,
+    pub treasury: Pubkey,
+    pub paid: bool,
+    pub op_counter: u64,
+    pub owners: Vec<Pubkey>,
+    pub subscribers: Vec<Pubkey>,
+    pub winners_list: Vec<Pubkey>,
+}
+
+impl StateV1 {
+    // v1 accounts were always sized for this many subscribers
+    pub const SUBSCRIBER_CAPACITY: u16 = 100;
+    // ...and this many winners
+    pub const WINNER_CAPACITY: u16 = 10;
+}
 
-    pub fn needs_migration(&self) -> bool {
-        self.version != Self::CURRENT_VERSION
+// Layout written by version 2 of the program: v1 plus the distribution deadline, same sizes.
+// Only used by migrate(), like StateV1.
+#[derive(AnchorSerialize, AnchorDeserialize)]
+pub struct StateV2 {
+    pub version: u8,
+    pub bump: u8,
+    pub challenge_id: u64,
+    pub fee: u64,
+    pub commission: u8,
+    pub status: u8,
+    pub owner: Pubkey,
+    pub treasury: Pubkey,
+    pub paid: bool,
+    pub op_counter: u64,
+    pub owners: Vec<Pubkey>,
+    pub subscribers: Vec<Pubkey>,
+    pub winners_list: Vec<Pubkey>,
+    pub distribution_deadline: i64,
+}
+
+impl State {
+    // Carry a v1 account over to the current layout; new fields get their neutral defaults
+    pub fn from_v1(old: StateV1) -> Self {
+        // v1 only had a flat fee, so that is what everyone paid; join times weren't recorded
+        let mut subscribers: Vec<Subscription> = old
+            .subscribers
+            .into_iter()
+            .map(|who| Subscription {
+                who,
+                paid: old.fee,
+                joined_at: 0,
+                referrer: Pubkey::default(),
+                fully_paid: true,
+            })
+            .collect();
+        subscribers.sort_by_key(|e| e.who); // binary-search membership needs a sorted list
+        let subscriber_count = subscribers.len() as u64;
+
+        let mut s = State {
+            version: Self::CURRENT_VERSION,
+            bump: old.bump,
+            challenge_id: old.challenge_id,
+            fee: old.fee,
+            commission: old.commission,
+            status: old.status,
+            owner: old.owner,
+            treasury: old.treasury,
+            paid: old.paid,
+            op_counter: old.op_counter,
+            owners: old.owners,
+            subscribers,
+            winners_list: old.winners_list,
+            distribution_deadline: 0,
+            subscriber_capacity: StateV1::SUBSCRIBER_CAPACITY,
+            distribution_nonce: if old.paid { 1 } else { 0 }, // a paid v1 challenge already distributed once
+            paid_winners: Vec::new(),
+            bonus_per_winner: 0,
+            commission_bps: 0,
+            metadata_hash: [0u8; 32],
+            sponsored_total: 0,
+            treasuries: Vec::new(),
+            winners_must_be_subscribers: false,
+            min_fee: 0,
+            max_fee: 0,
+            subscribe_start: 0,
+            subscribe_end: 0,
+            min_subscribers: 0,
+            refund_fee: 0,
+            randomness_commitment: [0u8; 32],
+            category: 0,
+            owners_cannot_subscribe: false,
+            total_collected: old.fee.saturating_mul(subscriber_count), // same backfill as paid
+            allow_program_winners: false,
+            winner_capacity: StateV1::WINNER_CAPACITY,
+            in_distribution: false,
+            creator: old.owner, // v1 PDAs were derived from the owner
+            ramp_base_fee: 0,
+            ramp_max_fee: 0,
+            ramp_start: 0,
+            ramp_end: 0,
+            max_tvl: 0,
+            distribution_frozen: false,
+            leftover_policy: 0,
+            merkle_root: [0u8; 32],
+            unclaimed_deadline: 0,
+            approved_commission_recipients: Vec::new(),
+            emergency_stopped: false,
+            commission_num: 0,
+            commission_den: 0,
+            mint: Pubkey::default(),
+            decimals: State::NATIVE_DECIMALS,
+            winners_lock_status: 0,
+            event_seq: 0,
+            winner_amounts: Vec::new(),
+            max_winner_ratio_bps: 0,
+            distributors: Vec::new(),
+            total_refunded: 0,
+            total_distributed: 0,
+            gate_collection: None,
+            installment_deadline: 0,
+            round_winners_up: false,
+            allow_owner_winners: false,
+            coupon_authority: None,
+            refund_window_secs: 0,
+            canceled_at: 0,
+            chunk_leftover: 0,
+            legacy_paid: old.paid, // total_distributed can't vouch for a v1 payout
+        };
+        s.set_commission_fraction(old.commission as u64, 100);
+        s
+    }
+
+    // A v2 account is a v1 account with a deadline; everything else carries over as from_v1 does
+    pub fn from_v2(old: StateV2) -> Self {
+        let mut s = Self::from_v1(StateV1 {
+            version: old.version,
+            bump: old.bump,
+            challenge_id: old.challenge_id,
+            fee: old.fee,
+            commission: old.commission,
+            status: old.status,
+            owner: old.owner,
+            treasury: old.treasury,
+            paid: old.paid,
+            op_counter: old.op_counter,
+            owners: old.owners,
+            subscribers: old.subscribers,
+            winners_list: old.winners_list,
+        });
+        s.distribution_deadline = old.distribution_deadline;
+        s
     }
 }
 
@@ -127,7 +929,7 @@ pub enum ErrorCode {
     // 6400–6499: Misc
     #[msg("Invalid commission rate")]
     InvalidCommissionRate = 6400,
-    #[msg("Invalid nonce - must be greater than current nonce")]
+    #[msg("Invalid nonce - must match current distribution nonce")]
     InvalidNonce,
     #[msg("Insufficient funds in vault")]
     InsufficientFunds,
@@ -161,6 +963,68 @@ pub enum ErrorCode {
     AlreadySubscribed,
     #[msg("Lamport arithmetic overflow/underflow")]
     LamportMathError,
+    #[msg("Distribution deadline not reached")]
+    DeadlineNotReached,
+    #[msg("No winners configured - set commission to 100 to send the whole pool to treasury")]
+    NoWinnersConfigured,
+    #[msg("Winner is not a subscriber")]
+    WinnerNotSubscriber,
+    #[msg("Signup window is not open yet")]
+    SignupWindowNotOpen,
+    #[msg("Signup window has not ended")]
+    SignupWindowNotEnded,
+    #[msg("No randomness commitment - call commit_randomness first")]
+    RandomnessNotCommitted,
+    #[msg("Owners cannot subscribe to this challenge")]
+    OwnerCannotSubscribe,
+    #[msg("Deposit would push the challenge balance above its TVL cap")]
+    TvlCapExceeded,
+    #[msg("State account needs migration - call migrate first")]
+    NeedsMigration,
+    #[msg("Distribution is frozen")]
+    DistributionFrozen,
+    #[msg("Subscriber is not on the allow-list")]
+    NotOnAllowList,
+    #[msg("Challenge was emergency stopped")]
+    ChallengeStopped,
+    #[msg("Mint account is not an initialized SPL mint with these decimals")]
+    InvalidMint,
+    #[msg("Treasury must be a system-owned wallet")]
+    TreasuryNotSystemAccount,
+    #[msg("Owner index is full")]
+    TooManyChallenges,
+    #[msg("Too many winners for the number of subscribers")]
+    WinnerRatioExceeded,
+    #[msg("Too many distributors")]
+    TooManyDistributors,
+    #[msg("Subscriber does not hold an NFT from the required collection")]
+    GateNotSatisfied,
+    #[msg("Distribution already moved funds and cannot be reopened")]
+    DistributionNotReopenable,
+    #[msg("Installment payments are not enabled")]
+    InstallmentsDisabled,
+    #[msg("Installment deadline has passed")]
+    InstallmentDeadlinePassed,
+    #[msg("Subscription is already fully paid")]
+    AlreadyFullyPaid,
+    #[msg("Subscriber has not paid the full fee")]
+    SubscriberNotFullyPaid,
+    #[msg("Owners cannot be winners of their own challenge")]
+    OwnerCannotBeWinner,
+    #[msg("Coupons are not enabled for this challenge")]
+    CouponsDisabled,
+    #[msg("Coupon signature missing or invalid")]
+    InvalidCoupon,
+    #[msg("Coupon already redeemed")]
+    CouponAlreadyUsed,
+    #[msg("Refund window for this subscriber has closed")]
+    RefundWindowClosed,
+    #[msg("Unclaimed deadline is inside the post-cancel grace period")]
+    UnclaimedGraceNotMet,
+    #[msg("A chunked payout is in progress")]
+    ChunkedPayoutInProgress,
+    #[msg("Leftover goes to the owner but owner_wallet was not passed")]
+    MissingOwnerWallet,
 }
 
 impl ErrorCode {
@@ -174,4 +1038,13 @@ impl ErrorCode {
         require!(is_owner || is_multi_owner, ErrorCode::OnlyAllowedUsers);
         Ok(())
     }
+    // Payouts only: any owner, or a wallet holding the distributor role
+    pub fn validate_distributor(signer: &Pubkey, state: &State) -> Result<()> {
+        let is_distributor = state.distributors.contains(signer);
+        require!(
+            is_distributor || Self::validate_allowed_user(signer, state).is_ok(),
+            ErrorCode::OnlyAllowedUsers
+        );
+        Ok(())
+    }
 }
@@ -1,6 +1,9 @@
 // Import Anchor framework for Solana 
 use anchor_lang::prelude::*;
 use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use anchor_lang::solana_program::entrypoint::MAX_PERMITTED_DATA_INCREASE;
+use anchor_lang::solana_program::hash;
 
 // Program ID for testing - backend team should update for mainnet
 declare_id!("C2DhNvJ4n4FEDyft6qcK3uDMjoRt5UU9mK41Zmn96VDz");
@@ -8,6 +11,8 @@ declare_id!("C2DhNvJ4n4FEDyft6qcK3uDMjoRt5UU9mK41Zmn96VDz");
 // Import our helper files
 mod internal;  // State data and error handling
 mod contexts;  // Account setups for each function
+#[cfg(test)]
+mod tests;     // Helper and handler tests
 
 use internal::*;
 use contexts::*;
@@ -18,12 +23,20 @@ fn pda_pay<'info>(
     to: &AccountInfo<'info>,
     amount: u64,
 ) -> Result<()> {
+    // Same account on both sides would double-borrow; treat as a bad recipient
+    require!(from.key != to.key, internal::ErrorCode::InvalidInput);
+
     let mut from_lamports = from.try_borrow_mut_lamports()?;
     let mut to_lamports   = to.try_borrow_mut_lamports()?;
 
     let new_from_balance = (*from_lamports)
         .checked_sub(amount)
         .ok_or(error!(internal::ErrorCode::InsufficientContractBalance))?;
+    // The PDA must stay rent exempt; catches payout/refund math that overshoots
+    require!(
+        new_from_balance >= Rent::get()?.minimum_balance(from.data_len()),
+        internal::ErrorCode::InsufficientContractBalance
+    );
     let new_to_balance = (*to_lamports)
         .checked_add(amount)
         .ok_or(error!(internal::ErrorCode::LamportMathError))?;
@@ -34,41 +47,555 @@ fn pda_pay<'info>(
     Ok(())
 }
 
+// Append winners to the list; shared by set_winners_list and set_winners_and_distribute
+fn add_winners(s: &mut State, winners: Vec<Pubkey>) -> Result<()> {
+    require_not_stopped(s)?;
+
+    // Freeze challenge after close - no modifications allowed
+    require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+
+    // Winners are locked once a chunked payout has started
+    require!(s.paid_winners.is_empty(), internal::ErrorCode::InvalidStatus);
+
+    // A fixed-amount list is only ever replaced whole by set_winners_with_amounts
+    require!(s.winner_amounts.is_empty(), internal::ErrorCode::InvalidInput);
+
+    // Strict modes: no winners before the challenge reaches the configured status
+    require!(s.status >= s.winners_lock_status, internal::ErrorCode::InvalidStatus);
+    
+    let winners_len = winners.len() as u64;
+    
+    // Add each winner to the list
+    for w in winners {
+        // Make sure winner address is valid (not empty)
+        require!(w != Pubkey::default(), internal::ErrorCode::InvalidWinnerAddress);
+        // Make sure no duplicates in existing list
+        require!(!s.winners_list.contains(&w), internal::ErrorCode::InvalidInput);
+        // Strict mode: winner must have joined
+        if s.winners_must_be_subscribers {
+            require!(s.is_subscriber(&w), internal::ErrorCode::WinnerNotSubscriber);
+        }
+        // A subscriber still owing an installment can't be picked
+        if s.is_subscriber(&w) {
+            require!(s.is_active_subscriber(&w), internal::ErrorCode::SubscriberNotFullyPaid);
+        }
+        // No self-dealing: organizers can't pay themselves prizes unless opted in
+        if !s.allow_owner_winners {
+            require!(
+                w != s.owner && !s.owners.contains(&w),
+                internal::ErrorCode::OwnerCannotBeWinner
+            );
+        }
+        // Treasury can't also collect a prize
+        require!(w != s.treasury, internal::ErrorCode::InvalidInput);
+        require!(
+            !s.approved_commission_recipients.contains(&w),
+            internal::ErrorCode::InvalidInput
+        );
+        require!(
+            !s.treasuries.iter().any(|t| t.wallet == w),
+            internal::ErrorCode::InvalidInput
+        );
+        // Make sure we don't have too many winners
+        require!(s.winners_list.len() < s.winner_capacity as usize, internal::ErrorCode::TooManyWinners);
+        // Add winner to the list
+        s.winners_list.push(w);
+    }
+    s.canonicalize_winners();
+
+    // Fairness cap: at most ratio of the field may win (0 = off)
+    if s.max_winner_ratio_bps != 0 {
+        let max_winners = s.subscribers.len() as u64 * s.max_winner_ratio_bps as u64
+            / State::BPS_DENOMINATOR as u64;
+        require!(
+            s.winners_list.len() as u64 <= max_winners,
+            internal::ErrorCode::WinnerRatioExceeded
+        );
+    }
+
+    // Routing the whole pool to treasury must be explicit (commission = 100)
+    require!(
+        !s.winners_list.is_empty() || s.is_full_commission(),
+        internal::ErrorCode::NoWinnersConfigured
+    );
+    
+    // Count this operation to be sure there is no loop
+    s.bump_op_counter(1 + winners_len);
+    Ok(())
+}
+
+// Route distribution dust per state.leftover_policy: 0 = treasury, 1 = primary owner,
+// 2 = leave it in the account for a later withdraw_excess. Policy 1 pays the stored owner's
+// wallet (owner_wallet), not the signer, so distributor and co-owner payouts work too.
+fn pay_leftover<'info>(
+    policy: u8,
+    state_ai: &AccountInfo<'info>,
+    treasury_ai: &AccountInfo<'info>,
+    owner_wallet: Option<&AccountInfo<'info>>,
+    leftover: u64,
+) -> Result<()> {
+    if leftover == 0 {
+        return Ok(());
+    }
+    match policy {
+        // owner_wallet is address-checked against state.owner by the context
+        1 => {
+            let owner_ai = owner_wallet.ok_or(error!(internal::ErrorCode::MissingOwnerWallet))?;
+            pda_pay(state_ai, owner_ai, leftover)
+        }
+        2 => Ok(()),
+        _ => pda_pay(state_ai, treasury_ai, leftover),
+    }
+}
+
+// Winner wallet checks shared by the payout paths
+fn validate_winner_account(
+    win_ai: &AccountInfo,
+    winner: &Pubkey,
+    state_key: &Pubkey,
+    treasury_key: &Pubkey,
+    allow_program_winners: bool,
+) -> Result<()> {
+    // Make sure the wallet matches the winner address
+    require!(*win_ai.key == *winner, internal::ErrorCode::InvalidWinnerAddress);
+    // Never "pay" the state PDA itself or the treasury (self-transfer griefing)
+    require!(*win_ai.key != *state_key, internal::ErrorCode::InvalidWinnerAddress);
+    require!(*win_ai.key != *treasury_key, internal::ErrorCode::InvalidWinnerAddress);
+    if allow_program_winners {
+        // Any owner is fine. Crediting lamports directly runs no code in the owning program,
+        // so there is no callback to re-enter us; that program only sees the balance later.
+        // Executable accounts can't have their balance changed by the runtime.
+        require!(!win_ai.executable, internal::ErrorCode::InvalidWinnerAddress);
+    } else {
+        // Make sure it's a regular Solana account
+        require!(
+            win_ai.owner == &system_program::ID,
+            internal::ErrorCode::InvalidWinnerAddress
+        );
+    }
+    Ok(())
+}
+
+// Pay commission: all to treasury_wallet in single mode, split by bps across state.treasuries otherwise
+fn pay_commission<'info>(
+    state_ai: &AccountInfo<'info>,
+    treasury_ai: &AccountInfo<'info>,
+    splits: &[TreasurySplit],
+    split_accounts: &[AccountInfo<'info>],
+    commission: u64,
+) -> Result<()> {
+    if commission == 0 {
+        return Ok(());
+    }
+    if splits.is_empty() {
+        return pda_pay(state_ai, treasury_ai, commission);
+    }
+
+    // Split wallets are passed in stored order
+    require!(
+        split_accounts.len() == splits.len(),
+        internal::ErrorCode::InvalidInput
+    );
+
+    let mut paid = 0u64;
+    for (i, split) in splits.iter().enumerate() {
+        let ai = &split_accounts[i];
+        require!(*ai.key == split.wallet, internal::ErrorCode::InvalidInput);
+        require!(ai.owner == &system_program::ID, internal::ErrorCode::TreasuryNotSystemAccount);
+
+        // Last wallet takes the rounding dust so the whole commission goes out
+        let share = if i + 1 == splits.len() {
+            commission - paid
+        } else {
+            commission_from_bps(commission, split.bps)
+        };
+        if share > 0 {
+            pda_pay(state_ai, ai, share)?;
+        }
+        paid += share;
+    }
+    Ok(())
+}
+
+// Append a challenge id to the owner's index, growing the account by one slot (owner pays rent)
+fn append_owner_index<'info>(
+    index: &mut Account<'info, OwnerIndex>,
+    owner: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    challenge_id: u64,
+) -> Result<()> {
+    require!(
+        index.challenge_ids.len() < OwnerIndex::MAX_CHALLENGES,
+        internal::ErrorCode::TooManyChallenges
+    );
+
+    let index_ai = index.to_account_info();
+    let new_len = OwnerIndex::space(index.challenge_ids.len() + 1);
+    let needed = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(index_ai.lamports());
+    if needed > 0 {
+        let cpi = CpiContext::new(
+            system_program.clone(),
+            system_program::Transfer {
+                from: owner.clone(),
+                to: index_ai.clone(),
+            },
+        );
+        system_program::transfer(cpi, needed)?;
+    }
+    index_ai.realloc(new_len, false)?;
+
+    // Written back into the larger buffer when the instruction exits
+    index.challenge_ids.push(challenge_id);
+    Ok(())
+}
+
+// Redeem a coupon once: the ("coupon", state, subscriber) PDA is funded for rent by the
+// subscriber and assigned to this program, and a program-owned marker means already used.
+// Assign rather than create_account, so lamports dropped on the address first can't block it.
+fn claim_coupon<'info>(
+    record: &AccountInfo<'info>,
+    state_key: &Pubkey,
+    subscriber: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let (expected, bump) = Pubkey::find_program_address(
+        &[b"coupon", state_key.as_ref(), subscriber.key.as_ref()],
+        &crate::ID,
+    );
+    require!(*record.key == expected, internal::ErrorCode::InvalidCoupon);
+    require!(*record.owner != crate::ID, internal::ErrorCode::CouponAlreadyUsed);
+
+    let needed = Rent::get()?.minimum_balance(0).saturating_sub(record.lamports());
+    if needed > 0 {
+        let cpi = CpiContext::new(
+            system_program.clone(),
+            system_program::Transfer {
+                from: subscriber.clone(),
+                to: record.clone(),
+            },
+        );
+        system_program::transfer(cpi, needed)?;
+    }
+
+    let seeds: &[&[u8]] = &[b"coupon", state_key.as_ref(), subscriber.key.as_ref(), &[bump]];
+    let signer = [seeds];
+    let cpi = CpiContext::new_with_signer(
+        system_program.clone(),
+        system_program::Assign { account_to_assign: record.clone() },
+        &signer,
+    );
+    system_program::assign(cpi, &crate::ID)
+}
+
+// Shared subscribe flow; referrer is Pubkey::default() when nobody referred the subscriber.
+// deposit = Some(amount) pays only part of the fee now, the rest is due via complete_payment.
+// discount_bps comes off the price first (coupons; 0 = full price).
+fn join_challenge(
+    ctx: Context<Subscribe>,
+    proof: Vec<[u8; 32]>,
+    referrer: Pubkey,
+    deposit: Option<u64>,
+    discount_bps: u16,
+) -> Result<()> {
+    require_not_stopped(&ctx.accounts.state)?;
+    let seq = ctx.accounts.state.next_event_seq();
+    // Get who is joining and how much they need to pay
+    let subscriber = ctx.accounts.subscriber.key();
+    let now = current_timestamp()?;
+    let price = ctx.accounts.state.current_fee(now); // Flat fee, or the ramp price right now
+    let price = apply_discount_bps(price, discount_bps);
+    let fee_amount = match deposit {
+        None => price,
+        Some(amount) => {
+            let s = &ctx.accounts.state;
+            require!(s.installment_deadline != 0, internal::ErrorCode::InstallmentsDisabled);
+            require!(now <= s.installment_deadline, internal::ErrorCode::InstallmentDeadlinePassed);
+            require!(amount > 0 && amount <= price, internal::ErrorCode::InvalidAmount);
+            amount
+        }
+    };
+
+    // Check if they can join (challenge open, not already joined, etc)
+    validate_subscription(&ctx.accounts.state, &subscriber, now)?;
+    require_no_chunked_payout(&ctx.accounts.state)?;
+    verify_allow_list(&ctx.accounts.state, &subscriber, &proof)?;
+    verify_collection_gate(
+        &ctx.accounts.state,
+        &subscriber,
+        ctx.accounts.gate_token_account.as_ref().map(|a| a.as_ref()),
+        ctx.accounts.gate_metadata.as_ref().map(|a| a.as_ref()),
+    )?;
+
+    // Make sure they have enough SOL to pay the fee
+    require!(
+        ctx.accounts.subscriber.lamports() >= fee_amount,
+        internal::ErrorCode::InsufficientBalance
+    );
+    validate_tvl(&ctx.accounts.state, ctx.accounts.state.to_account_info().lamports(), fee_amount)?;
+
+    // Transfer SOL from subscriber to challenge account
+    let cpi = CpiContext::new(
+        ctx.accounts.system_program.to_account_info(),
+        system_program::Transfer {
+            from: ctx.accounts.subscriber.to_account_info(),  // From subscriber
+            to: ctx.accounts.state.to_account_info(),        // To challenge account
+        },
+    );
+    system_program::transfer(cpi, fee_amount)?;
+
+    // Add them to the list of participants
+    let s = &mut ctx.accounts.state;
+    s.insert_subscriber(subscriber, fee_amount, now, referrer, fee_amount >= price);
+    s.total_collected = s
+        .total_collected
+        .checked_add(fee_amount)
+        .ok_or(error!(internal::ErrorCode::LamportMathError))?;
+
+    // Tell everyone someone joined
+    emit!(SubscriptionCreated {
+        challenge_id: s.challenge_id,
+        subscriber,
+        timestamp: now, // same value stored as joined_at
+        seq,
+    });
+
+    if referrer != Pubkey::default() {
+        emit!(ReferralRecorded {
+            challenge_id: s.challenge_id,
+            subscriber,
+            referrer,
+            seq,
+        });
+    }
+
+    Ok(())
+}
+
+// Shared setup for initialize and initialize_sized; optional settings keep their zero defaults
+#[allow(clippy::too_many_arguments)]
+fn init_state(
+    s: &mut State,
+    owner: Pubkey,
+    bump: u8,
+    challenge_id: u64,
+    fee: u64,
+    commission: u8,
+    treasury: Pubkey,
+    subscriber_capacity: u16,
+    winner_capacity: u16,
+) -> Result<()> {
+    // Validate inputs
+    require!(treasury != Pubkey::default(), internal::ErrorCode::InvalidInput);
+    require!(commission <= 100, internal::ErrorCode::InvalidCommissionRate);
+    require!(fee > 0, internal::ErrorCode::InvalidAmount);
+    // Capacity must be within the hard max and creatable in one instruction
+    require!(
+        subscriber_capacity > 0 && subscriber_capacity as usize <= State::MAX_SUBSCRIBERS,
+        internal::ErrorCode::InvalidInput
+    );
+    require!(
+        winner_capacity > 0 && winner_capacity as usize <= State::MAX_WINNERS,
+        internal::ErrorCode::InvalidInput
+    );
+    require!(
+        State::space(subscriber_capacity as usize, winner_capacity as usize)
+            <= MAX_PERMITTED_DATA_INCREASE,
+        internal::ErrorCode::InvalidInput
+    );
+
+    // Set up the challenge with initial values
+    s.version = State::CURRENT_VERSION;  // Version number
+    s.bump = bump;                      // Security value
+    s.challenge_id = challenge_id;      // Store challenge ID
+    s.fee = fee;                       // Store entry fee
+    s.set_commission_fraction(commission as u64, 100); // Store commission rate (percent, bps + fraction)
+    s.status = 0;                     // 0 = PENDING (not started yet)
+    s.creator = owner;                // PDA seed, never changes
+    s.owner = owner;                  // Primary owner (can be transferred)
+    s.treasury = treasury;            // Store treasury wallet
+    s.paid = false;                   // Distribution not yet run
+    s.op_counter = 0;                 // Count of operations
+    s.owners = vec![s.owner];         // List of people who can manage
+    s.subscribers = Vec::new();       // Empty list of participants
+    s.winners_list = Vec::new();      // Empty list of winners
+    s.subscriber_capacity = subscriber_capacity;     // Slots the account holds
+    s.winner_capacity = winner_capacity;             // Winner slots the account holds
+    s.distribution_nonce = 0;                        // First distribution expects 0
+    s.paid_winners = Vec::new();                     // Chunked payout ledger
+    s.bonus_per_winner = 0;                          // Fixed by the first chunk
+    s.sponsored_total = 0;                           // No sponsor money yet
+    s.treasuries = Vec::new();                       // Single-treasury mode
+    s.decimals = State::NATIVE_DECIMALS;             // Native SOL until configure_token
+
+    // Treasury can't collide with anyone on the lists
+    validate_treasury(s, &treasury)
+}
+
+// refund_all's transfer loop; seq and now are the caller's, so cancel_and_refund can share them
+fn refund_every_subscriber<'info>(
+    ctx: Context<'_, '_, '_, 'info, RefundBatch<'info>>,
+    seq: u64,
+    now: i64,
+) -> Result<()> {
+    let s = &ctx.accounts.state;
+
+    let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+    let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+    // Sponsor money is never refundable
+    let refundable = s.refundable_balance(total, rent_exempt);
+
+    let count = s.subscribers.len();
+    let need = s.total_paid()?;
+
+    require!(refundable >= need, internal::ErrorCode::InsufficientContractBalance);
+    s.check_refund_budget(need)?;
+
+    require!(
+        ctx.remaining_accounts.len() == count,
+        internal::ErrorCode::MissingSubscriberAccount
+    );
+
+    let mut deducted: u64 = 0;
+    for (i, e) in s.subscribers.iter().enumerate() {
+        let sub_ai = ctx.remaining_accounts.get(i).unwrap();
+        require!(*sub_ai.key == e.who, internal::ErrorCode::InvalidSubscriberAddress);
+        require!(
+            sub_ai.owner == &system_program::ID,
+            internal::ErrorCode::InvalidSubscriberAddress
+        );
+
+        // Subscriber gets what they paid minus any processing fee
+        let amount = e.paid.saturating_sub(s.refund_fee);
+        deducted += e.paid - amount;
+        if amount > 0 {
+            pda_pay(
+                &ctx.accounts.state.to_account_info(),
+                &sub_ai.to_account_info(),
+                amount,
+            )?;
+        }
+
+        emit!(RefundSent {
+            challenge_id: s.challenge_id,
+            subscriber: e.who,
+            timestamp: now,
+            amount,
+            seq,
+        });
+    }
+
+    // Processing fees go to treasury in one transfer
+    if deducted > 0 {
+        pda_pay(
+            &ctx.accounts.state.to_account_info(),
+            &ctx.accounts.treasury_wallet.to_account_info(),
+            deducted,
+        )?;
+    }
+
+    // Everyone was refunded
+    let s_mut = &mut ctx.accounts.state;
+    s_mut.subscribers.clear();
+    s_mut.total_refunded = s_mut.total_refunded.saturating_add(need);
+    s_mut.bump_op_counter(1 + count as u64);
+
+    Ok(())
+}
+
 // Main 
 #[program]
 pub mod snzup_subscription {
     use super::*;
 
     // Create a new challenge 
+    #[allow(clippy::too_many_arguments)]
     pub fn initialize(
         ctx: Context<Initialize>,
         challenge_id: u64,    // Unique number for this challenge
         fee: u64,            // How much people pay to join (in lamports)
         commission: u8,      // Percentage company takes (0-100)
         treasury: Pubkey,    // Treasury wallet for payouts
+        distribution_deadline: i64, // After this, subscribers can force refunds (0 = never)
+        subscriber_capacity: u16,   // Subscriber slots to size the account for
+        winner_capacity: u16,       // Winner slots to size the account for
+        metadata_hash: [u8; 32],    // Hash of the off-chain rules document
+        winners_must_be_subscribers: bool, // Strict mode: only subscribers can win
+        min_fee: u64,        // Lowest fee set_fee may use (0 = no floor)
+        max_fee: u64,        // Highest fee set_fee may use (0 = no cap)
+        category: u8,        // Challenge type for off-chain filtering
+        owners_cannot_subscribe: bool, // Opt-in: owners can't join
+        allow_program_winners: bool,   // Opt-in: winners may be program-owned accounts
+        max_tvl: u64,        // Cap on the challenge balance (0 = unlimited)
+        leftover_policy: u8, // 0 = treasury, 1 = owner, 2 = leave in account
+        winners_lock_status: u8, // Earliest status winners may be set at (0 = any time)
+        max_winner_ratio_bps: u16, // Cap on winners as a share of subscribers (0 = no cap)
+        round_winners_up: bool,    // Winners get the rounded-up share; commission covers it
+        allow_owner_winners: bool, // Opt-in: owner/owners may be picked as winners
     ) -> Result<()> {
+        let seq = ctx.accounts.state.next_event_seq();
         // Validate inputs
-        require!(treasury != Pubkey::default(), internal::ErrorCode::InvalidInput);
-        require!(commission <= 100, internal::ErrorCode::InvalidCommissionRate);
-        require!(fee > 0, internal::ErrorCode::InvalidAmount);
-        
+        require!(leftover_policy <= 2, internal::ErrorCode::InvalidInput);
+        require!(winners_lock_status <= 2, internal::ErrorCode::InvalidStatus);
+        require!(
+            max_winner_ratio_bps <= State::BPS_DENOMINATOR,
+            internal::ErrorCode::InvalidInput
+        );
+        require!(category <= State::MAX_CATEGORY, internal::ErrorCode::InvalidInput);
+        require!(
+            min_fee == 0 || max_fee == 0 || min_fee <= max_fee,
+            internal::ErrorCode::InvalidInput
+        );
+        validate_fee_bounds(fee, min_fee, max_fee)?;
+        // Deadline must be in the future when set
+        require!(
+            distribution_deadline == 0 || distribution_deadline > current_timestamp()?,
+            internal::ErrorCode::InvalidInput
+        );
+
+        let owner = ctx.accounts.owner.key();
+        let bump = ctx.bumps.state;
+
         // Get the state account we just created
         let s = &mut ctx.accounts.state;
-        
-        // Set up the challenge with initial values
-        s.version = State::CURRENT_VERSION;  // Version number
-        s.bump = ctx.bumps.state;           // Security value
-        s.challenge_id = challenge_id;      // Store challenge ID
-        s.fee = fee;                       // Store entry fee
-        s.commission = commission;         // Store commission rate
-        s.status = 0;                     // 0 = PENDING (not started yet)
-        s.owner = ctx.accounts.owner.key(); // Who created this challenge
-        s.treasury = treasury;            // Store treasury wallet
-        s.paid = false;                   // Distribution not yet run
-        s.op_counter = 0;                 // Count of operations
-        s.owners = vec![s.owner];         // List of people who can manage
-        s.subscribers = Vec::new();       // Empty list of participants
-        s.winners_list = Vec::new();      // Empty list of winners
+        init_state(
+            s,
+            owner,
+            bump,
+            challenge_id,
+            fee,
+            commission,
+            treasury,
+            subscriber_capacity,
+            winner_capacity,
+        )?;
+
+        // Optional settings
+        s.distribution_deadline = distribution_deadline; // Refund escape hatch
+        s.metadata_hash = metadata_hash;                 // Anchor to off-chain rules
+        s.winners_must_be_subscribers = winners_must_be_subscribers; // Opt-in strict winners
+        s.min_fee = min_fee;                             // Fee guardrail floor
+        s.max_fee = max_fee;                             // Fee guardrail cap
+        s.category = category;                           // Dashboard grouping
+        s.owners_cannot_subscribe = owners_cannot_subscribe; // Opt-in self-entry block
+        s.allow_program_winners = allow_program_winners; // Opt-in PDA/vault winners
+        s.max_tvl = max_tvl;                             // Risk cap on locked SOL
+        s.leftover_policy = leftover_policy;             // Where distribution dust goes
+        s.winners_lock_status = winners_lock_status;     // Earliest status for winners
+        s.max_winner_ratio_bps = max_winner_ratio_bps;   // Fairness cap on winners
+        s.round_winners_up = round_winners_up;           // Rounding direction for bonus_each
+        s.allow_owner_winners = allow_owner_winners;     // Opt-in owner winners
+
+        // Record the challenge in the owner's index when one is passed
+        if let Some(index) = ctx.accounts.owner_index.as_mut() {
+            append_owner_index(
+                index,
+                &ctx.accounts.owner.to_account_info(),
+                &ctx.accounts.system_program.to_account_info(),
+                challenge_id,
+            )?;
+        }
         
         // Emit initialization event
         emit!(Initialized {
@@ -76,346 +603,2588 @@ pub mod snzup_subscription {
             owner: s.owner,
             treasury,
             version: s.version,
+            category,
+            seq,
         });
         
         Ok(())
     }
 
+    // Create the owner's challenge index; later initialize calls that pass it append to it
+    pub fn init_owner_index(ctx: Context<InitOwnerIndex>) -> Result<()> {
+        let index = &mut ctx.accounts.owner_index;
+        index.owner = ctx.accounts.owner.key();
+        index.bump = ctx.bumps.owner_index;
+        index.challenge_ids = Vec::new();
+        Ok(())
+    }
+
+    // Create a challenge sized for exactly expected_subscribers (lower rent for small challenges);
+    // winners get the default slots and every optional setting starts off
+    pub fn initialize_sized(
+        ctx: Context<InitializeSized>,
+        challenge_id: u64,
+        fee: u64,
+        commission: u8,
+        treasury: Pubkey,
+        expected_subscribers: u16,
+    ) -> Result<()> {
+        let seq = ctx.accounts.state.next_event_seq();
+        require!(
+            expected_subscribers as usize <= State::MAX_SUBSCRIBERS,
+            internal::ErrorCode::MaxSubscribersReached
+        );
+
+        let owner = ctx.accounts.owner.key();
+        let bump = ctx.bumps.state;
+
+        let s = &mut ctx.accounts.state;
+        init_state(
+            s,
+            owner,
+            bump,
+            challenge_id,
+            fee,
+            commission,
+            treasury,
+            expected_subscribers,
+            State::DEFAULT_WINNER_CAPACITY,
+        )?;
+
+        emit!(Initialized {
+            challenge_id,
+            owner: s.owner,
+            treasury,
+            version: s.version,
+            category: s.category,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    // Upgrade an older state account in place to the current layout (owner-only)
+    pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
+        let state_ai = ctx.accounts.state.to_account_info();
+        require!(state_ai.owner == ctx.program_id, internal::ErrorCode::InvalidInput);
+
+        // Read the old layout by hand; the version byte right after the discriminator picks it
+        let (from_version, mut migrated) = {
+            let data = state_ai.try_borrow_data()?;
+            require!(
+                data.len() > 8 && data[..8] == State::DISCRIMINATOR,
+                internal::ErrorCode::InvalidInput
+            );
+            require!(data[8] < State::CURRENT_VERSION, internal::ErrorCode::AlreadyMigrated);
+            let from_version = data[8];
+            let mut migrated = match from_version {
+                1 => State::from_v1(StateV1::deserialize(&mut &data[8..])?),
+                2 => State::from_v2(StateV2::deserialize(&mut &data[8..])?),
+                _ => return err!(internal::ErrorCode::InvalidInput),
+            };
+            // Neither old layout recorded when it was canceled; the grace period starts at migration
+            if migrated.status == 3 {
+                migrated.canceled_at = current_timestamp()?;
+            }
+            (from_version, migrated)
+        };
+        migrated.version = State::CURRENT_VERSION;
+
+        // Same checks the State contexts do: primary owner and PDA derivation
+        validate_primary_owner(&ctx.accounts.owner.key(), &migrated.owner)?;
+        let expected = Pubkey::create_program_address(
+            &[
+                b"state",
+                migrated.creator.as_ref(),
+                &migrated.challenge_id.to_le_bytes(),
+                &[migrated.bump],
+            ],
+            ctx.program_id,
+        )
+        .map_err(|_| error!(internal::ErrorCode::InvalidInput))?;
+        require!(expected == state_ai.key(), internal::ErrorCode::InvalidInput);
+
+        let seq = migrated.next_event_seq();
+
+        // Grow the account if the new layout needs more room; owner tops up rent
+        let new_len = State::space(
+            migrated.subscriber_capacity as usize,
+            migrated.winner_capacity as usize,
+        );
+        if state_ai.data_len() < new_len {
+            let needed = Rent::get()?
+                .minimum_balance(new_len)
+                .saturating_sub(state_ai.lamports());
+            if needed > 0 {
+                let cpi = CpiContext::new(
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.owner.to_account_info(),
+                        to: state_ai.clone(),
+                    },
+                );
+                system_program::transfer(cpi, needed)?;
+            }
+            state_ai.realloc(new_len, false)?;
+        }
+
+        // Write the current layout back after the discriminator
+        {
+            let mut data = state_ai.try_borrow_mut_data()?;
+            let mut dst: &mut [u8] = &mut data[8..];
+            migrated
+                .serialize(&mut dst)
+                .map_err(|_| error!(anchor_lang::error::ErrorCode::AccountDidNotSerialize))?;
+        }
+
+        emit!(Migrated {
+            from_version,
+            to_version: State::CURRENT_VERSION,
+            seq,
+        });
+        emit!(StateVersion {
+            challenge_id: migrated.challenge_id,
+            version: migrated.version,
+            seq,
+        });
+        Ok(())
+    }
+
     // Join a challenge by paying the fee
-    pub fn subscribe(ctx: Context<Subscribe>) -> Result<()> {
-        // Get who is joining and how much they need to pay
+    // proof: Merkle proof for invite-only challenges (empty when the challenge is open)
+    pub fn subscribe(ctx: Context<Subscribe>, proof: Vec<[u8; 32]>) -> Result<()> {
+        join_challenge(ctx, proof, Pubkey::default(), None, 0)
+    }
+
+    // Join with part of the fee; the subscriber can't win until complete_payment settles the rest
+    pub fn subscribe_deposit(ctx: Context<Subscribe>, amount: u64, proof: Vec<[u8; 32]>) -> Result<()> {
+        join_challenge(ctx, proof, Pubkey::default(), Some(amount), 0)
+    }
+
+    // Pay the balance of an installment subscription before installment_deadline. The price is
+    // the fee at joined_at, which is stable because pricing is frozen once anyone has joined.
+    pub fn complete_payment(ctx: Context<CompletePayment>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
         let subscriber = ctx.accounts.subscriber.key();
-        let fee_amount = {
+        let now = current_timestamp()?;
+
+        let (idx, remainder) = {
             let s = &ctx.accounts.state;
-            s.fee  // Get the fee amount from challenge state
+            require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+            require!(s.installment_deadline != 0, internal::ErrorCode::InstallmentsDisabled);
+            require!(now <= s.installment_deadline, internal::ErrorCode::InstallmentDeadlinePassed);
+            let idx = s
+                .subscriber_index(&subscriber)
+                .map_err(|_| error!(internal::ErrorCode::InvalidSubscriberAddress))?;
+            let entry = s.subscribers[idx];
+            require!(!entry.fully_paid, internal::ErrorCode::AlreadyFullyPaid);
+            let remainder = s
+                .current_fee(entry.joined_at)
+                .checked_sub(entry.paid)
+                .ok_or(error!(internal::ErrorCode::LamportMathError))?;
+            (idx, remainder)
         };
 
-        // Check if they can join (challenge open, not already joined, etc)
-        validate_subscription(&ctx.accounts.state, &subscriber)?;
-
-        // Make sure they have enough SOL to pay the fee
         require!(
-            ctx.accounts.subscriber.lamports() >= fee_amount,
+            ctx.accounts.subscriber.lamports() >= remainder,
             internal::ErrorCode::InsufficientBalance
         );
+        validate_tvl(&ctx.accounts.state, ctx.accounts.state.to_account_info().lamports(), remainder)?;
 
-        // Transfer SOL from subscriber to challenge account
         let cpi = CpiContext::new(
             ctx.accounts.system_program.to_account_info(),
             system_program::Transfer {
-                from: ctx.accounts.subscriber.to_account_info(),  // From subscriber
-                to: ctx.accounts.state.to_account_info(),        // To challenge account
+                from: ctx.accounts.subscriber.to_account_info(),
+                to: ctx.accounts.state.to_account_info(),
             },
         );
-        system_program::transfer(cpi, fee_amount)?;
+        system_program::transfer(cpi, remainder)?;
 
-        // Add them to the list of participants
         let s = &mut ctx.accounts.state;
-        s.subscribers.push(subscriber);
-
-        // Tell everyone someone joined
-        emit!(SubscriptionCreated {
+        let entry = &mut s.subscribers[idx];
+        entry.paid = entry
+            .paid
+            .checked_add(remainder)
+            .ok_or(error!(internal::ErrorCode::LamportMathError))?;
+        entry.fully_paid = true;
+        let total_paid = entry.paid;
+        s.total_collected = s
+            .total_collected
+            .checked_add(remainder)
+            .ok_or(error!(internal::ErrorCode::LamportMathError))?;
+        s.bump_op_counter(1);
+
+        emit!(PaymentCompleted {
             challenge_id: s.challenge_id,
             subscriber,
-            timestamp: Clock::get()?.unix_timestamp,
+            amount: remainder,
+            total_paid,
+            seq,
         });
-
         Ok(())
     }
 
-    // Set who won the challenge 
-    pub fn set_winners_list(ctx: Context<OnlyOwner>, winners: Vec<Pubkey>) -> Result<()> {
+    // Deadline for installment balances (0 turns installments off); PENDING only
+    pub fn set_installment_deadline(ctx: Context<OnlyOwner>, installment_deadline: i64) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
         let s = &mut ctx.accounts.state;
-        
-        // Freeze challenge after close - no modifications allowed
-        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
-        
-        let winners_len = winners.len() as u64;
-        
-        // Add each winner to the list
-        for w in winners {
-            // Make sure winner address is valid (not empty)
-            require!(w != Pubkey::default(), internal::ErrorCode::InvalidWinnerAddress);
-            // Make sure no duplicates in existing list
-            require!(!s.winners_list.contains(&w), internal::ErrorCode::InvalidInput);
-            // Make sure we don't have too many winners
-            require!(s.winners_list.len() < State::MAX_WINNERS, internal::ErrorCode::TooManyWinners);
-            // Add winner to the list
-            s.winners_list.push(w);
+        s.next_event_seq();
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+        if installment_deadline != 0 {
+            require!(
+                installment_deadline > current_timestamp()?,
+                internal::ErrorCode::InvalidInput
+            );
         }
-        
-        // Count this operation to be sure there is no loop
-        s.op_counter = s.op_counter.saturating_add(1 + winners_len);
+
+        s.installment_deadline = installment_deadline;
+        s.bump_op_counter(1);
         Ok(())
     }
 
-    // Owner-only
-    pub fn remove_owner(ctx: Context<OnlyOwner>, user: Pubkey) -> Result<()> {
-        let s = &mut ctx.accounts.state;
-        
-        // Only allow owner changes while not CLOSED
-        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
-        
-        if let Some(i) = s.owners.iter().position(|x| *x == user) {
-            s.owners.remove(i);
-        }
-        Ok(())
+    // Same as subscribe, but records who invited the subscriber
+    pub fn subscribe_with_referral(
+        ctx: Context<Subscribe>,
+        referrer: Pubkey,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        require!(referrer != Pubkey::default(), internal::ErrorCode::InvalidInput);
+        // Can't refer yourself
+        require!(referrer != ctx.accounts.subscriber.key(), internal::ErrorCode::InvalidInput);
+        join_challenge(ctx, proof, referrer, None, 0)
     }
 
-    // Status setter
-    pub fn set_status(ctx: Context<OnlyOwner>, status: u8) -> Result<()> {
-        // 0=PENDING 1=IN_PROGRESS 2=CLOSED 3=CANCELED
-        require!(status <= 3, internal::ErrorCode::InvalidStatus);
-        let s = &mut ctx.accounts.state;
-        
-        // Forbid changing away from CLOSED if paid == true
-        if s.paid {
-            require!(status == 2, internal::ErrorCode::InvalidStatus);
-        }
-        
-        s.status = status;
-        s.op_counter = s.op_counter.saturating_add(1);
-        Ok(())
+    // Join at a discount signed off-chain by coupon_authority. The transaction must carry an
+    // Ed25519 program instruction right before this one, verifying coupon_authority's signature
+    // over state || subscriber || discount_bps (u16 LE) || challenge_id (u64 LE). Pass instructions_sysvar
+    // and the ("coupon", state, subscriber) PDA as coupon_record; one redemption per subscriber.
+    pub fn subscribe_with_coupon(
+        ctx: Context<Subscribe>,
+        discount_bps: u16,
+        signature: [u8; 64],
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let authority = ctx
+            .accounts
+            .state
+            .coupon_authority
+            .ok_or(error!(internal::ErrorCode::CouponsDisabled))?;
+        require!(discount_bps <= State::BPS_DENOMINATOR, internal::ErrorCode::InvalidInput);
+
+        let message = coupon_message(
+            &ctx.accounts.state.key(),
+            &ctx.accounts.subscriber.key(),
+            discount_bps,
+            ctx.accounts.state.challenge_id,
+        );
+
+        let instructions = ctx
+            .accounts
+            .instructions_sysvar
+            .as_ref()
+            .ok_or(error!(internal::ErrorCode::InvalidCoupon))?;
+        verify_coupon(instructions, &authority, &signature, &message)?;
+
+        let record = ctx
+            .accounts
+            .coupon_record
+            .as_ref()
+            .ok_or(error!(internal::ErrorCode::InvalidCoupon))?;
+        claim_coupon(
+            &record.to_account_info(),
+            &ctx.accounts.state.key(),
+            &ctx.accounts.subscriber.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+
+        join_challenge(ctx, proof, Pubkey::default(), None, discount_bps)
     }
 
-    // Remove subscriber + emit event
-    pub fn cancel_subscription(ctx: Context<OnlyOwner>, subscriber: Pubkey) -> Result<()> {
+    // Key that signs discount coupons (None turns coupons off); PENDING only
+    pub fn set_coupon_authority(ctx: Context<OnlyOwner>, coupon_authority: Option<Pubkey>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
         let s = &mut ctx.accounts.state;
-        
-        // Freeze challenge after close - no modifications allowed
-        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
-        
-        if let Some(i) = s.subscribers.iter().position(|x| *x == subscriber) {
-            s.subscribers.remove(i);
-            emit!(SubscriptionCancelled {
-                challenge_id: s.challenge_id,
-                subscriber,
-                timestamp: Clock::get()?.unix_timestamp,
-            });
-        }
+        s.next_event_seq();
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+
+        s.coupon_authority = coupon_authority;
+        s.bump_op_counter(1);
         Ok(())
     }
 
-    // Send prize money to winners and commission to the company
-    pub fn send_bonus_to_winners<'info>(
-        ctx: Context<'_, '_, '_, 'info, SendBonus<'info>>,
-    ) -> Result<()> {
-        // Check if the person calling this is allowed to distribute prizes
-        validate_allowed_user(&ctx.accounts.owner.key(), &ctx.accounts.state)?;
+    // Join with a fee ceiling so a front-run set_fee can't raise the price mid-flight
+    pub fn subscribe_exact(ctx: Context<Subscribe>, max_fee: u64, proof: Vec<[u8; 32]>) -> Result<()> {
+        // Client signed for at most max_fee; reject if the on-chain fee moved above it
+        require!(
+            ctx.accounts.state.current_fee(current_timestamp()?) <= max_fee,
+            internal::ErrorCode::InvalidAmount
+        );
 
-        let s = &ctx.accounts.state;
-        
-        // Require distribution hasn't been run yet
-        require!(!s.paid, internal::ErrorCode::InvalidStatus);
-        
-        // Block payout if challenge is canceled
-        require!(s.status != 3, internal::ErrorCode::InvalidStatus); // 3 = CANCELED
-        
-        // Require treasury wallet matches state.treasury
+        // Same flow as a normal subscribe
+        subscribe(ctx, proof)
+    }
+
+    // Join several participants in one transaction; wallets come in remaining_accounts as signers
+    pub fn subscribe_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, SubscribeBatch<'info>>,
+        subscribers: Vec<Pubkey>,
+        proofs: Vec<Vec<[u8; 32]>>, // One proof per subscriber for invite-only challenges, else empty
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        require!(!subscribers.is_empty(), internal::ErrorCode::InvalidInput);
+        // Gated challenges need per-subscriber NFT proof; use subscribe
         require!(
-            ctx.accounts.treasury_wallet.key() == s.treasury,
+            ctx.accounts.state.gate_collection.is_none(),
+            internal::ErrorCode::GateNotSatisfied
+        );
+        require!(
+            proofs.is_empty() || proofs.len() == subscribers.len(),
             internal::ErrorCode::InvalidInput
         );
 
-        // Calculate how much money we need to keep in account (rent)
-        let rent_exempt = Rent::get()?.minimum_balance(State::MAX_SIZE);
-        // Get total money in challenge account
-        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        // Make sure the whole batch fits before moving any SOL
+        require!(
+            ctx.accounts.state.subscribers.len() + subscribers.len()
+                <= ctx.accounts.state.subscriber_capacity as usize,
+            internal::ErrorCode::MaxSubscribersReached
+        );
 
-        // Make sure we have money to distribute
-        require!(total > rent_exempt, internal::ErrorCode::InsufficientContractBalance);
+        require!(
+            ctx.remaining_accounts.len() == subscribers.len(),
+            internal::ErrorCode::MissingSubscriberAccount
+        );
 
-        // Calculate available money (total - rent)
-        let available = total - rent_exempt;
-        let commission_rate = s.commission as u64;     // Company's cut percentage
-        let winners_len = s.winners_list.len() as u64; // How many winners
+        // Whole batch joins at the same instant, so one ramp price
+        let now = current_timestamp()?;
+        let fee_amount = ctx.accounts.state.current_fee(now);
+        let state_ai = ctx.accounts.state.to_account_info();
+        let system_ai = ctx.accounts.system_program.to_account_info();
 
-        require!(available > 0, internal::ErrorCode::InsufficientContractBalance);
+        for (i, sub) in subscribers.iter().enumerate() {
+            // Same checks as a single subscribe; also catches duplicates inside the batch
+            validate_subscription(&ctx.accounts.state, sub, now)?;
+            verify_allow_list(
+                &ctx.accounts.state,
+                sub,
+                proofs.get(i).map(|p| p.as_slice()).unwrap_or(&[]),
+            )?;
 
-        // Calculate company commission (percentage of available money)
-        let commission = available * commission_rate / 100;
-        let prize_pool = available - commission;
-        let bonus_each = if winners_len == 0 { 0 } else { prize_pool / winners_len };
+            let sub_ai = ctx.remaining_accounts.get(i).unwrap();
+            require!(*sub_ai.key == *sub, internal::ErrorCode::InvalidSubscriberAddress);
+            require!(sub_ai.is_signer, internal::ErrorCode::Unauthorized);
+            require!(
+                sub_ai.lamports() >= fee_amount,
+                internal::ErrorCode::InsufficientBalance
+            );
+            validate_tvl(&ctx.accounts.state, state_ai.lamports(), fee_amount)?;
+
+            // Any failed transfer aborts the whole transaction
+            let cpi = CpiContext::new(
+                system_ai.clone(),
+                system_program::Transfer {
+                    from: sub_ai.clone(),
+                    to: state_ai.clone(),
+                },
+            );
+            system_program::transfer(cpi, fee_amount)?;
+
+            let s = &mut ctx.accounts.state;
+            s.insert_subscriber(*sub, fee_amount, now, Pubkey::default(), true);
+            s.total_collected = s
+                .total_collected
+                .checked_add(fee_amount)
+                .ok_or(error!(internal::ErrorCode::LamportMathError))?;
+
+            emit!(SubscriptionCreated {
+                challenge_id: s.challenge_id,
+                subscriber: *sub,
+                timestamp: now, // same value stored as joined_at
+                seq,
+            });
+        }
+
+        Ok(())
+    }
+
+    // Sponsor top-up: grows the prize pool without touching subscriber refunds
+    pub fn sponsor_deposit(ctx: Context<SponsorDeposit>, amount: u64) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        require!(amount > 0, internal::ErrorCode::InvalidAmount);
+
+        // Nothing can be paid out of a closed or canceled challenge
+        require!(
+            !is_terminal_status(ctx.accounts.state.status),
+            internal::ErrorCode::InvalidStatus
+        );
+
+        require!(
+            ctx.accounts.sponsor.lamports() >= amount,
+            internal::ErrorCode::InsufficientBalance
+        );
+        validate_tvl(&ctx.accounts.state, ctx.accounts.state.to_account_info().lamports(), amount)?;
+
+        // Transfer SOL from sponsor to challenge account
+        let cpi = CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.sponsor.to_account_info(),
+                to: ctx.accounts.state.to_account_info(),
+            },
+        );
+        system_program::transfer(cpi, amount)?;
+
+        let s = &mut ctx.accounts.state;
+        s.sponsored_total = s
+            .sponsored_total
+            .checked_add(amount)
+            .ok_or(error!(internal::ErrorCode::LamportMathError))?;
+
+        emit!(SponsorDeposited {
+            challenge_id: s.challenge_id,
+            sponsor: ctx.accounts.sponsor.key(),
+            amount,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    // Set who won the challenge 
+    pub fn set_winners_list(ctx: Context<OnlyOwner>, winners: Vec<Pubkey>) -> Result<()> {
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        add_winners(s, winners)
+    }
+
+    // Store winners and pay out in one transaction (winner wallets in remaining_accounts)
+    pub fn set_winners_and_distribute<'info>(
+        ctx: Context<'_, '_, '_, 'info, SendBonus<'info>>,
+        winners: Vec<Pubkey>,
+    ) -> Result<()> {
+        // Same permission as set_winners_list
+        validate_primary_owner(&ctx.accounts.owner.key(), &ctx.accounts.state.owner)?;
+
+        add_winners(&mut ctx.accounts.state, winners)?;
+
+        // Nothing can land in between, so the current nonce is the expected one
+        let nonce = ctx.accounts.state.distribution_nonce;
+        send_bonus_to_winners(ctx, nonce, None, 0, 0)
+    }
+
+    // Swap in a recomputed winner set in one call; same checks as set_winners_list
+    pub fn replace_winners(ctx: Context<OnlyOwner>, winners: Vec<Pubkey>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+        require!(!s.paid, internal::ErrorCode::InvalidStatus);
+
+        s.winners_list.clear();
+        s.winner_amounts.clear();
+        add_winners(s, winners)?;
+
+        emit!(WinnersReplaced {
+            challenge_id: s.challenge_id,
+            count: s.winners_list.len() as u32,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Replace the winners with a prize ladder: winners[i] gets exactly amounts[i] at distribute_fixed
+    pub fn set_winners_with_amounts(
+        ctx: Context<OnlyOwner>,
+        winners: Vec<Pubkey>,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        require!(!winners.is_empty(), internal::ErrorCode::InvalidInput);
+        require!(winners.len() == amounts.len(), internal::ErrorCode::InvalidInput);
+        require!(amounts.iter().all(|a| *a > 0), internal::ErrorCode::InvalidAmount);
+
+        // add_winners applies the usual winner checks to the new list
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        s.winners_list.clear();
+        s.winner_amounts.clear();
+        add_winners(s, winners.clone())?;
+        // add_winners sorted the winners; lay the amounts out to match
+        s.winner_amounts = s
+            .winners_list
+            .iter()
+            .map(|w| amounts[winners.iter().position(|x| x == w).unwrap()])
+            .collect();
+        Ok(())
+    }
+
+    // Commit to sha256(seed) before the seed is revealed; can only be set once
+    pub fn commit_randomness(ctx: Context<OnlyOwner>, commitment: [u8; 32]) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+
+        // Only while the challenge is still open
+        require!(s.status <= 1, internal::ErrorCode::InvalidStatus);
+        require!(commitment != [0u8; 32], internal::ErrorCode::InvalidInput);
+        // No re-committing after seeing who joined
+        require!(s.randomness_commitment == [0u8; 32], internal::ErrorCode::InvalidInput);
+
+        s.randomness_commitment = commitment;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Reveal the committed seed and pick num_winners from the (sorted) subscribers.
+    // Round k hashes sha256(seed || k as u8), takes the first 8 bytes as a little-endian u64
+    // and removes pool[value % pool.len()]. The pool starts as the sorted subscriber list and
+    // keeps its order, so anyone can replay the pick from the seed and the account data.
+    // Subscribers still owing an installment are left out of the pool.
+    pub fn reveal_and_select(ctx: Context<OnlyOwner>, seed: [u8; 32], num_winners: u8) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+
+        require!(s.randomness_commitment != [0u8; 32], internal::ErrorCode::RandomnessNotCommitted);
+        require!(
+            hash::hash(&seed).to_bytes() == s.randomness_commitment,
+            internal::ErrorCode::InvalidInput
+        );
+        require!(
+            num_winners > 0 && num_winners as u16 <= s.winner_capacity,
+            internal::ErrorCode::TooManyWinners
+        );
+        let mut pool: Vec<Pubkey> = s
+            .subscribers
+            .iter()
+            .filter(|e| e.fully_paid)
+            .map(|e| e.who)
+            .collect();
+        require!(num_winners as usize <= pool.len(), internal::ErrorCode::InvalidInput);
+
+        let mut picked = Vec::with_capacity(num_winners as usize);
+        for k in 0..num_winners {
+            let h = hash::hashv(&[&seed, &[k]]).to_bytes();
+            let mut word = [0u8; 8];
+            word.copy_from_slice(&h[..8]);
+            let idx = (u64::from_le_bytes(word) % pool.len() as u64) as usize;
+            picked.push(pool.remove(idx));
+        }
+
+        // Replace any manual list; add_winners applies the usual winner checks
+        s.winners_list.clear();
+        s.winner_amounts.clear();
+        add_winners(s, picked)?;
+
+        emit!(WinnersSelected {
+            challenge_id: s.challenge_id,
+            seed,
+            winners: s.winners_list.clone(),
+            seq,
+        });
+        Ok(())
+    }
+
+    // Remove a single winner (e.g. disqualified before payout) + emit event
+    pub fn remove_winner(ctx: Context<OnlyOwner>, winner: Pubkey) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+
+        // Freeze challenge after close - no modifications allowed
+        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+
+        // Winners are locked once a chunked payout has started
+        require!(s.paid_winners.is_empty(), internal::ErrorCode::InvalidStatus);
+
+        if let Some(i) = s.winners_list.iter().position(|x| *x == winner) {
+            s.winners_list.remove(i);
+            // Keep a fixed-amount list parallel
+            if !s.winner_amounts.is_empty() {
+                s.winner_amounts.remove(i);
+            }
+            s.bump_op_counter(1);
+            emit!(WinnerRemoved {
+                challenge_id: s.challenge_id,
+                winner,
+                seq,
+            });
+        }
+        Ok(())
+    }
+
+    // Add subscriber slots; the account is reallocated and the owner pays the extra rent
+    pub fn grow_capacity(ctx: Context<GrowCapacity>, additional: u16) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+
+        // No layout changes once the challenge is closed
+        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+        require!(additional > 0, internal::ErrorCode::InvalidInput);
+
+        let new_capacity = s.subscriber_capacity as usize + additional as usize;
+        require!(new_capacity <= State::MAX_SUBSCRIBERS, internal::ErrorCode::InvalidInput);
+
+        // One realloc can only add MAX_PERMITTED_DATA_INCREASE bytes; bigger growth takes
+        // several calls
+        let state_ai = s.to_account_info();
+        let new_len = State::space(new_capacity, s.winner_capacity as usize);
+        require!(
+            new_len.saturating_sub(state_ai.data_len()) <= MAX_PERMITTED_DATA_INCREASE,
+            internal::ErrorCode::InvalidInput
+        );
+
+        // Owner tops up rent for the extra space
+        let needed = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(state_ai.lamports());
+        if needed > 0 {
+            let cpi = CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.owner.to_account_info(),
+                    to: state_ai.clone(),
+                },
+            );
+            system_program::transfer(cpi, needed)?;
+        }
+        state_ai.realloc(new_len, false)?;
+
+        s.subscriber_capacity = new_capacity as u16;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Owner-only
+    pub fn remove_owner(ctx: Context<OnlyOwner>, user: Pubkey) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        
+        // Only allow owner changes while not CLOSED
+        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+        
+        if let Some(i) = s.owners.iter().position(|x| *x == user) {
+            s.owners.remove(i);
+        }
+        Ok(())
+    }
+
+    // Configure when signups open/close and the minimum field size (PENDING only)
+    pub fn set_signup_window(
+        ctx: Context<OnlyOwner>,
+        subscribe_start: i64,
+        subscribe_end: i64,
+        min_subscribers: u16,
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+
+        // End must be in the future and after the start when set
+        if subscribe_end != 0 {
+            require!(
+                subscribe_end > current_timestamp()?,
+                internal::ErrorCode::InvalidInput
+            );
+            require!(subscribe_start < subscribe_end, internal::ErrorCode::InvalidInput);
+        }
+        require!(
+            min_subscribers as usize <= s.subscriber_capacity as usize,
+            internal::ErrorCode::InvalidInput
+        );
+
+        s.subscribe_start = subscribe_start;
+        s.subscribe_end = subscribe_end;
+        s.min_subscribers = min_subscribers;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Relaunch a canceled challenge with a fresh signup window (same account, no new rent)
+    pub fn reset_for_retry(
+        ctx: Context<OnlyOwner>,
+        new_subscribe_start: i64,
+        new_subscribe_end: i64,
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let now = current_timestamp()?;
+        let s = &mut ctx.accounts.state;
+
+        require!(s.status == 3, internal::ErrorCode::InvalidStatus); // Only from CANCELED
+        require!(!s.paid, internal::ErrorCode::InvalidStatus);
+        // Everyone must have been refunded first
+        require!(s.subscribers.is_empty(), internal::ErrorCode::InvalidStatus);
+        require!(s.paid_winners.is_empty(), internal::ErrorCode::InvalidStatus);
+
+        // Same window rules as set_signup_window
+        if new_subscribe_end != 0 {
+            require!(
+                new_subscribe_end > now,
+                internal::ErrorCode::InvalidInput
+            );
+            require!(new_subscribe_start < new_subscribe_end, internal::ErrorCode::InvalidInput);
+        }
+
+        s.status = 0; // 0 = PENDING
+        s.subscribe_start = new_subscribe_start;
+        s.subscribe_end = new_subscribe_end;
+        s.winners_list.clear();
+        s.winner_amounts.clear();
+        s.randomness_commitment = [0u8; 32];
+        s.bump_op_counter(1);
+
+        emit!(ChallengeReset {
+            challenge_id: s.challenge_id,
+            subscribe_start: new_subscribe_start,
+            subscribe_end: new_subscribe_end,
+            timestamp: now,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Anyone can close signups after subscribe_end: too few subscribers cancels, otherwise starts
+    pub fn finalize_signup_window(ctx: Context<Crank>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let now = current_timestamp()?;
+        let s = &mut ctx.accounts.state;
+
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus); // Only from PENDING
+        require!(s.subscribe_end != 0, internal::ErrorCode::SignupWindowNotEnded);
+        require!(
+            now > s.subscribe_end,
+            internal::ErrorCode::SignupWindowNotEnded
+        );
+
+        let from = s.status;
+        if s.subscribers.len() < s.min_subscribers as usize {
+            s.mark_canceled(now);
+        } else {
+            s.status = 1; // 1 = IN_PROGRESS
+        }
+        s.bump_op_counter(1);
+
+        emit!(StatusChanged {
+            challenge_id: s.challenge_id,
+            from,
+            to: s.status,
+            timestamp: now,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Anyone can refresh status from the stored windows. Only PENDING -> IN_PROGRESS past
+    // subscribe_end is automatic; closing needs winners, and an undersized field is left for
+    // finalize_signup_window to cancel. A poke with nothing to advance is a no-op.
+    pub fn poke(ctx: Context<Crank>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let now = current_timestamp()?;
+        let s = &ctx.accounts.state;
+        if s.status != 0
+            || s.subscribe_end == 0
+            || now <= s.subscribe_end
+            || s.subscribers.len() < s.min_subscribers as usize
+        {
+            return Ok(());
+        }
+
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+        let from = s.status;
+        s.status = 1; // 1 = IN_PROGRESS
+        s.bump_op_counter(1);
+
+        emit!(StatusAutoAdvanced {
+            challenge_id: s.challenge_id,
+            from,
+            to: s.status,
+            timestamp: now,
+            seq,
+        });
+        Ok(())
+    }
+
+    // How long after joining a subscriber can still be refunded individually (0 = always):
+    // self_refund, refund_batch, refund_range, refund_to and cancel_and_refund_one check it.
+    // Challenge-wide refunds (refund_all, cancel_and_refund, force_refund_*) ignore it.
+    pub fn set_refund_window(ctx: Context<OnlyOwner>, refund_window_secs: i64) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+        require!(s.subscribers.is_empty(), internal::ErrorCode::InvalidStatus);
+        require!(refund_window_secs >= 0, internal::ErrorCode::InvalidInput);
+
+        s.refund_window_secs = refund_window_secs;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Processing fee kept from each admin refund (PENDING, before anyone joins)
+    pub fn set_refund_fee(ctx: Context<OnlyOwner>, refund_fee: u64) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+        require!(s.subscribers.is_empty(), internal::ErrorCode::InvalidStatus);
+        require!(refund_fee <= s.fee, internal::ErrorCode::InvalidAmount);
+        // Nobody on the ramp can pay less than the processing fee
+        require!(
+            s.ramp_end == 0 || refund_fee <= s.ramp_base_fee,
+            internal::ErrorCode::InvalidAmount
+        );
+
+        s.refund_fee = refund_fee;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Change the challenge category while PENDING
+    pub fn set_category(ctx: Context<OnlyOwner>, category: u8) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+        require!(category <= State::MAX_CATEGORY, internal::ErrorCode::InvalidInput);
+
+        s.category = category;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Late-join ramp: fee rises linearly from base_fee at ramp_start to max_fee at ramp_end.
+    // ramp_end = 0 turns the ramp off and subscribe charges the flat fee again.
+    pub fn set_fee_ramp(
+        ctx: Context<OnlyOwner>,
+        base_fee: u64,
+        max_fee: u64,
+        ramp_start: i64,
+        ramp_end: i64,
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+
+        // Economics are frozen once the first person has paid
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+        require!(s.subscribers.is_empty(), internal::ErrorCode::InvalidStatus);
+
+        if ramp_end != 0 {
+            require!(base_fee > 0 && base_fee <= max_fee, internal::ErrorCode::InvalidAmount);
+            require!(ramp_start < ramp_end, internal::ErrorCode::InvalidInput);
+            // Both ends stay inside the fee guardrails and above the refund processing fee
+            validate_fee_bounds(base_fee, s.min_fee, s.max_fee)?;
+            validate_fee_bounds(max_fee, s.min_fee, s.max_fee)?;
+            require!(base_fee >= s.refund_fee, internal::ErrorCode::InvalidAmount);
+        }
+
+        s.ramp_base_fee = base_fee;
+        s.ramp_max_fee = max_fee;
+        s.ramp_start = ramp_start;
+        s.ramp_end = ramp_end;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Hold payouts pending a dispute. Frozen blocks send_bonus_to_winners, send_bonus_chunk and
+    // set_winners_and_distribute; subscribe, refunds, cancel and winner edits keep working.
+    // There is no separate subscribe pause - signups are controlled by status and the window.
+    pub fn freeze_distribution(ctx: Context<OnlyOwner>, frozen: bool) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        s.distribution_frozen = frozen;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Break-glass halt: cancels and freezes in one call, and can't be undone.
+    // Afterwards only refunds and sweep_unclaimed (the close step for a canceled challenge) run.
+    pub fn emergency_stop(ctx: Context<OnlyOwner>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+
+        s.mark_canceled(current_timestamp()?);
+        s.distribution_frozen = true;
+        s.emergency_stopped = true;
+        s.bump_op_counter(1);
+
+        emit!(EmergencyStopped {
+            challenge_id: s.challenge_id,
+            by: ctx.accounts.owner.key(),
+            seq,
+        });
+        Ok(())
+    }
+
+    // Restrict signups to holders of an NFT collection (None opens the challenge)
+    pub fn set_gate_collection(ctx: Context<OnlyOwner>, gate_collection: Option<Pubkey>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+
+        s.gate_collection = gate_collection;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Invite-only allow-list root (zeros opens the challenge to everyone)
+    pub fn set_merkle_root(ctx: Context<OnlyOwner>, merkle_root: [u8; 32]) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+
+        s.merkle_root = merkle_root;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Status setter
+    pub fn set_status(ctx: Context<OnlyOwner>, status: u8) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        // 0=PENDING 1=IN_PROGRESS 2=CLOSED 3=CANCELED
+        require!(status <= 3, internal::ErrorCode::InvalidStatus);
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        
+        // CLOSED and CANCELED are terminal (paid always implies CLOSED);
+        // only PENDING <-> IN_PROGRESS is freely reversible
+        if is_terminal_status(s.status) {
+            require!(status == s.status, internal::ErrorCode::InvalidStatus);
+        }
+        
+        if status == 3 {
+            s.mark_canceled(current_timestamp()?);
+        } else {
+            s.status = status;
+        }
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Remove subscriber + emit event
+    pub fn cancel_subscription(ctx: Context<OnlyOwner>, subscriber: Pubkey) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+        
+        // Freeze challenge after close - no modifications allowed
+        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+        
+        if let Ok(i) = s.subscriber_index(&subscriber) {
+            s.subscribers.remove(i);
+            emit!(SubscriptionCancelled {
+                challenge_id: s.challenge_id,
+                subscriber,
+                timestamp: current_timestamp()?,
+                seq,
+            });
+        }
+        Ok(())
+    }
+
+    // A subscriber leaves on their own while signups are open (or after an emergency stop),
+    // within refund_window_secs of joining, and gets back what they paid minus any refund fee
+    pub fn self_refund(ctx: Context<SelfRefund>) -> Result<()> {
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let subscriber = ctx.accounts.subscriber.key();
+        let now = current_timestamp()?;
+        let s = &ctx.accounts.state;
+
+        require!(s.status == 0 || s.emergency_stopped, internal::ErrorCode::InvalidStatus);
+        let idx = s
+            .subscriber_index(&subscriber)
+            .map_err(|_| error!(internal::ErrorCode::InvalidSubscriberAddress))?;
+        let paid = s.subscribers[idx].paid;
+        s.check_refund_window(s.subscribers[idx].joined_at, now)?;
+
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        // Sponsor money is never refundable
+        let refundable = s.refundable_balance(total, rent_exempt);
+        require!(refundable >= paid, internal::ErrorCode::InsufficientContractBalance);
+        s.check_refund_budget(paid)?;
+
+        // Same processing fee as any other refund
+        let amount = paid.saturating_sub(s.refund_fee);
+        let deducted = paid - amount;
+
+        if amount > 0 {
+            pda_pay(
+                &ctx.accounts.state.to_account_info(),
+                &ctx.accounts.subscriber.to_account_info(),
+                amount,
+            )?;
+        }
+        if deducted > 0 {
+            pda_pay(
+                &ctx.accounts.state.to_account_info(),
+                &ctx.accounts.treasury_wallet.to_account_info(),
+                deducted,
+            )?;
+        }
+
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.subscribers.remove(idx);
+        s_mut.total_refunded = s_mut.total_refunded.saturating_add(paid);
+        s_mut.bump_op_counter(1);
+
+        emit!(SubscriptionCancelled {
+            challenge_id: s_mut.challenge_id,
+            subscriber,
+            timestamp: now,
+            seq,
+        });
+        emit!(RefundSent {
+            challenge_id: s_mut.challenge_id,
+            subscriber,
+            timestamp: now,
+            amount,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Remove a subscriber and send back what they paid (minus any refund fee).
+    // cancel_subscription stays for refunds already settled off-chain.
+    pub fn cancel_and_refund_one(ctx: Context<CancelAndRefundOne>, subscriber: Pubkey) -> Result<()> {
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let now = current_timestamp()?;
+        let s = &ctx.accounts.state;
+
+        // Freeze challenge after close - no modifications allowed
+        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+
+        let idx = s
+            .subscriber_index(&subscriber)
+            .map_err(|_| error!(internal::ErrorCode::InvalidSubscriberAddress))?;
+        let paid = s.subscribers[idx].paid;
+        s.check_refund_window(s.subscribers[idx].joined_at, now)?;
+
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        // Sponsor money is never refundable
+        let refundable = s.refundable_balance(total, rent_exempt);
+        require!(refundable >= paid, internal::ErrorCode::InsufficientContractBalance);
+        s.check_refund_budget(paid)?;
+
+        // Same processing fee as any other admin refund
+        let amount = paid.saturating_sub(s.refund_fee);
+        let deducted = paid - amount;
+
+        if amount > 0 {
+            pda_pay(
+                &ctx.accounts.state.to_account_info(),
+                &ctx.accounts.subscriber_wallet.to_account_info(),
+                amount,
+            )?;
+        }
+        if deducted > 0 {
+            pda_pay(
+                &ctx.accounts.state.to_account_info(),
+                &ctx.accounts.treasury_wallet.to_account_info(),
+                deducted,
+            )?;
+        }
+
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.subscribers.remove(idx);
+        s_mut.total_refunded = s_mut.total_refunded.saturating_add(paid);
+        s_mut.bump_op_counter(1);
+
+        emit!(SubscriptionCancelled {
+            challenge_id: s_mut.challenge_id,
+            subscriber,
+            timestamp: now,
+            seq,
+        });
+        emit!(RefundSent {
+            challenge_id: s_mut.challenge_id,
+            subscriber,
+            timestamp: now,
+            amount,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Send prize money to winners and commission to the company
+    // expected_nonce must equal state.distribution_nonce. The paid latch already blocks a second
+    // payout; the nonce makes a retried transaction that already landed fail with a clear
+    // InvalidNonce instead, so clients can tell "already done" apart from other failures.
+    // Winner wallets come first in remaining_accounts in winners_list order (ascending pubkey, see
+    // canonicalize_winners), split treasury wallets after.
+    // Every winner is a remaining account, so large winner lists won't fit one transaction
+    // (account and compute limits); pay those with send_bonus_chunk instead.
+    // commission_recipient overrides state.treasury for this payout (commission and any leftover
+    // bound for treasury); it must be pre-approved and passed as treasury_wallet.
+    // expected_available/tolerance pin the balance the client showed: the payout fails with
+    // InvalidAmount if available moved by more than tolerance (both zero = no check).
+    pub fn send_bonus_to_winners<'info>(
+        ctx: Context<'_, '_, '_, 'info, SendBonus<'info>>,
+        expected_nonce: u64,
+        commission_recipient: Option<Pubkey>,
+        expected_available: u64,
+        tolerance: u64,
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let now = current_timestamp()?;
+        // Check if the person calling this is allowed to distribute prizes
+        validate_distributor(&ctx.accounts.owner.key(), &ctx.accounts.state)?;
+        require_current_version(&ctx.accounts.state.to_account_info())?;
+        require!(!ctx.accounts.state.distribution_frozen, internal::ErrorCode::DistributionFrozen);
+
+        // Reentrancy guard. Defense in depth only: pda_pay runs no foreign code, and the
+        // account is written back when the instruction exits, so a nested call would never see
+        // the flag set; the paid latch is what actually blocks a second payout.
+        require!(!ctx.accounts.state.in_distribution, internal::ErrorCode::InvalidStatus);
+        ctx.accounts.state.in_distribution = true;
+        // Accounts stored before canonical ordering get sorted here
+        ctx.accounts.state.canonicalize_winners();
+
+        let s = &ctx.accounts.state;
+        
+        // Reject stale or replayed distribution attempts
+        require!(s.distribution_nonce == expected_nonce, internal::ErrorCode::InvalidNonce);
+
+        // Require distribution hasn't been run yet
+        require!(!s.paid, internal::ErrorCode::InvalidStatus);
+
+        // A chunked distribution already started must be finished with send_bonus_chunk
+        require!(s.paid_winners.is_empty(), internal::ErrorCode::InvalidStatus);
+
+        // Fixed-amount winners are paid with distribute_fixed
+        require!(s.winner_amounts.is_empty(), internal::ErrorCode::InvalidInput);
+
+        // Winners never set: the whole pool would leave as leftover unless commission is 100
+        require!(
+            !s.winners_list.is_empty() || s.is_full_commission(),
+            internal::ErrorCode::NoWinnersConfigured
+        );
+        
+        // Block payout if challenge is canceled
+        require!(s.status != 3, internal::ErrorCode::InvalidStatus); // 3 = CANCELED
+        
+        // Require treasury wallet matches state.treasury, or the approved override
+        let treasury = match commission_recipient {
+            Some(recipient) => {
+                require!(
+                    s.approved_commission_recipients.contains(&recipient),
+                    internal::ErrorCode::InvalidInput
+                );
+                // Split mode already routes commission per wallet
+                require!(s.treasuries.is_empty(), internal::ErrorCode::InvalidInput);
+                recipient
+            }
+            None => s.treasury,
+        };
+        require!(
+            ctx.accounts.treasury_wallet.key() == treasury,
+            internal::ErrorCode::InvalidInput
+        );
+
+        // Cheapest failing check first: every winner wallet plus any split treasury wallets must be
+        // passed before anything is computed or transferred
+        require!(
+            ctx.remaining_accounts.len() == s.winners_list.len() + s.treasuries.len(),
+            internal::ErrorCode::MissingWinnerAccount
+        );
+
+        // Calculate how much money we need to keep in account (rent)
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        // Get total money in challenge account
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+
+        // Make sure we have money to distribute
+        require!(total > rent_exempt, internal::ErrorCode::InsufficientContractBalance);
+
+        // Calculate available money (total - rent); checked even though the require above covers it
+        let available = total
+            .checked_sub(rent_exempt)
+            .ok_or(error!(internal::ErrorCode::InsufficientContractBalance))?;
+        let (commission_num, commission_den) = s.commission_fraction(); // Company's cut as a fraction
+        let winners_len = s.winners_list.len() as u64; // How many winners
+
+        require!(available > 0, internal::ErrorCode::InsufficientContractBalance);
+
+        // Slippage guard: e.g. a sponsor deposit landing after the client built the transaction
+        if expected_available != 0 || tolerance != 0 {
+            require!(
+                available.abs_diff(expected_available) <= tolerance,
+                internal::ErrorCode::InvalidAmount
+            );
+        }
+
+        // Calculate company commission (fraction of available money) and the equal split
+        let BonusBreakdown {
+            commission_amount: commission,
+            bonus_pool: prize_pool,
+            winner_amount: bonus_each,
+        } = calculate_competition_bonus(
+            available,
+            commission_num,
+            commission_den,
+            winners_len as usize,
+            s.round_winners_up,
+        )?;
         let leftover = available - commission - (bonus_each * winners_len);
 
         emit!(CommisionAndBonusCalculation {
             balance: available,
             challenge_balance: prize_pool,
-            timestamp: Clock::get()?.unix_timestamp
+            timestamp: now,
+            seq,
+        });
+
+        emit!(CommisionAndBonusCalculated {
+            commission,
+            bonus: bonus_each,
+            timestamp: now,
+            seq,
+        });
+
+        // Checks-effects-interactions: latch paid and close before any lamports move.
+        // Solana has no EVM-style re-entry here (pda_pay runs no foreign code and the account is
+        // written back on exit), but effects-first removes the question for auditors.
+        // A failed transfer below reverts the whole transaction, latch included.
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.paid = true;
+        s_mut.status = 2;  // 2 = CLOSED
+        s_mut.distribution_nonce = s_mut.distribution_nonce.saturating_add(1);
+        s_mut.bump_op_counter(1 + winners_len);
+        let s = &ctx.accounts.state;
+
+        // Send company commission to treasury wallet(s); split wallets follow the winners
+        pay_commission(
+            &ctx.accounts.state.to_account_info(),
+            &ctx.accounts.treasury_wallet.to_account_info(),
+            &s.treasuries,
+            &ctx.remaining_accounts[winners_len as usize..],
+            commission,
+        )?;
+
+        // Send prize money to each winner
+        if bonus_each > 0 {
+            // Winner wallets follow winners_list position by position
+            let winner_accounts = &ctx.remaining_accounts[..winners_len as usize];
+            for (i, winner) in s.winners_list.iter().enumerate() {
+                // Make sure winner address is valid
+                require!(*winner != Pubkey::default(), internal::ErrorCode::InvalidWinnerAddress);
+
+                // Get winner's wallet from the accounts passed in (key checked below)
+                let win_ai = &winner_accounts[i];
+                validate_winner_account(
+                    win_ai,
+                    winner,
+                    &ctx.accounts.state.key(),
+                    &ctx.accounts.treasury_wallet.key(),
+                    s.allow_program_winners,
+                )?;
+
+                // Send prize money to winner
+                pda_pay(
+                    &ctx.accounts.state.to_account_info(),
+                    &win_ai.to_account_info(),
+                    bonus_each,
+                )?;
+
+                // Tell this winner got paid
+                emit!(BonusSent {
+                    challenge_id: s.challenge_id,
+                    subscriber: *winner,
+                    timestamp: now,
+                    amount: bonus_each,
+                    seq,
+                });
+            }
+        }
+
+        // Send the leftover where the challenge's policy says
+        pay_leftover(
+            s.leftover_policy,
+            &ctx.accounts.state.to_account_info(),
+            &ctx.accounts.treasury_wallet.to_account_info(),
+            ctx.accounts.owner_wallet.as_ref().map(|a| a.as_ref()),
+            leftover,
+        )?;
+
+        // Payout done; release the reentrancy guard
+        let moved = total.saturating_sub(ctx.accounts.state.to_account_info().lamports());
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.in_distribution = false;
+        s_mut.total_distributed = s_mut.total_distributed.saturating_add(moved);
+
+        // One-shot record of the whole payout for reconciliation; total_distributed is what
+        // actually left the PDA, so a leftover kept under policy 2 is not counted
+        let winners_paid = if bonus_each > 0 { winners_len } else { 0 };
+        emit!(DistributionSummary {
+            challenge_id: s_mut.challenge_id,
+            total_distributed: moved,
+            commission,
+            winners_paid: winners_paid as u32,
+            leftover,
+            timestamp: now,
+            leftover_policy: s_mut.leftover_policy,
+            seq,
+        });
+
+        // Emit terminal event for clean archival: what this call actually transferred
+        emit!(ChallengeClosed {
+            challenge_id: s_mut.challenge_id,
+            timestamp: now,
+            final_commission_paid: commission,
+            final_prizes_paid: bonus_each * winners_paid,
+            subscribers_at_close: s_mut.subscribers.len() as u32,
+            winners_at_close: s_mut.winners_list.len() as u32,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    // Balance split the way distribution sees it; rent uses the real account size like the payout
+    pub fn get_available(ctx: Context<ReadState>) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        emit!(AvailableBalance {
+            challenge_id: ctx.accounts.state.challenge_id,
+            total,
+            rent_exempt,
+            available: total.saturating_sub(rent_exempt),
+            seq,
+        });
+        Ok(())
+    }
+
+    // Show what send_bonus_to_winners would pay right now, without moving anything
+    pub fn preview_distribution(ctx: Context<ReadState>) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        let s = &ctx.accounts.state;
+
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        let available = total.saturating_sub(rent_exempt);
+        let winners_len = s.winners_list.len() as u64;
+
+        // Same math as the real run
+        let BonusBreakdown {
+            commission_amount: commission,
+            winner_amount: bonus_each,
+            ..
+        } = {
+            let (num, den) = s.commission_fraction();
+            calculate_competition_bonus(available, num, den, winners_len as usize, s.round_winners_up)?
+        };
+        let leftover = available - commission - (bonus_each * winners_len);
+
+        emit!(DistributionPreview {
+            challenge_id: s.challenge_id,
+            available,
+            commission,
+            bonus_each,
+            winners_count: winners_len as u32,
+            leftover,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Pay winners_list[start..start+count] so a large payout can span several transactions.
+    // Chunks must run in order without gaps; the first one snapshots the balance, fixes the split
+    // and leftover and pays commission, the last one routes that leftover and closes the challenge.
+    // expected_nonce is the same on every chunk and only advances when the last one closes.
+    // Subscribes, deposits, refunds and cancels are rejected until then (require_no_chunked_payout).
+    pub fn send_bonus_chunk<'info>(
+        ctx: Context<'_, '_, '_, 'info, SendBonus<'info>>,
+        expected_nonce: u64,
+        start: u32,
+        count: u32,
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let now = current_timestamp()?;
+        // Check if the person calling this is allowed to distribute prizes
+        validate_distributor(&ctx.accounts.owner.key(), &ctx.accounts.state)?;
+        require_current_version(&ctx.accounts.state.to_account_info())?;
+        require!(!ctx.accounts.state.distribution_frozen, internal::ErrorCode::DistributionFrozen);
+
+        // Same defense-in-depth guard as send_bonus_to_winners
+        require!(!ctx.accounts.state.in_distribution, internal::ErrorCode::InvalidStatus);
+        ctx.accounts.state.in_distribution = true;
+        // No-op once the first chunk has paid, so indices stay stable across chunks
+        ctx.accounts.state.canonicalize_winners();
+
+        let s = &ctx.accounts.state;
+
+        // Same guards as the single-shot distribution; the nonce only moves on the last chunk
+        require!(s.distribution_nonce == expected_nonce, internal::ErrorCode::InvalidNonce);
+        require!(!s.paid, internal::ErrorCode::InvalidStatus);
+        require!(s.winner_amounts.is_empty(), internal::ErrorCode::InvalidInput);
+        require!(s.status != 3, internal::ErrorCode::InvalidStatus); // 3 = CANCELED
+        require!(
+            ctx.accounts.treasury_wallet.key() == s.treasury,
+            internal::ErrorCode::InvalidInput
+        );
+
+        // Next chunk must start exactly where the last one ended
+        let winners_len = s.winners_list.len();
+        let start = start as usize;
+        let end = start
+            .checked_add(count as usize)
+            .ok_or(error!(internal::ErrorCode::InvalidInput))?;
+        require!(count > 0, internal::ErrorCode::InvalidInput);
+        require!(start == s.paid_winners.len(), internal::ErrorCode::InvalidInput);
+        require!(end <= winners_len, internal::ErrorCode::InvalidInput);
+
+        // One wallet per winner in this chunk, same order as winners_list;
+        // the first chunk also carries the split treasury wallets after the winners
+        let split_len = if start == 0 { s.treasuries.len() } else { 0 };
+        require!(
+            ctx.remaining_accounts.len() == count as usize + split_len,
+            internal::ErrorCode::MissingWinnerAccount
+        );
+
+        let state_ai = ctx.accounts.state.to_account_info();
+        let treasury_ai = ctx.accounts.treasury_wallet.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(state_ai.data_len());
+        let balance_before = state_ai.lamports();
+
+        // First chunk snapshots the balance: it fixes the split and the leftover and pays
+        // commission; later chunks reuse the snapshot, so nothing arriving mid-payout is swept
+        let (bonus_each, commission_paid, leftover) = if start == 0 {
+            let total = state_ai.lamports();
+            require!(total > rent_exempt, internal::ErrorCode::InsufficientContractBalance);
+
+            let available = total
+                .checked_sub(rent_exempt)
+                .ok_or(error!(internal::ErrorCode::InsufficientContractBalance))?;
+            let BonusBreakdown {
+                commission_amount: commission,
+                bonus_pool: prize_pool,
+                winner_amount: bonus_each,
+            } = {
+                let (num, den) = s.commission_fraction();
+                calculate_competition_bonus(available, num, den, winners_len, s.round_winners_up)?
+            };
+
+            emit!(CommisionAndBonusCalculation {
+                balance: available,
+                challenge_balance: prize_pool,
+                timestamp: now,
+                seq,
+            });
+
+            emit!(CommisionAndBonusCalculated {
+                commission,
+                bonus: bonus_each,
+                timestamp: now,
+                seq,
+            });
+
+            pay_commission(
+                &state_ai,
+                &treasury_ai,
+                &s.treasuries,
+                &ctx.remaining_accounts[count as usize..],
+                commission,
+            )?;
+            let leftover = available - commission - bonus_each * winners_len as u64;
+            (bonus_each, commission, leftover)
+        } else {
+            (s.bonus_per_winner, 0, s.chunk_leftover)
+        };
+
+        let chunk = s.winners_list[start..end].to_vec();
+        for (i, winner) in chunk.iter().enumerate() {
+            let win_ai = ctx.remaining_accounts.get(i).unwrap();
+            validate_winner_account(
+                win_ai,
+                winner,
+                state_ai.key,
+                treasury_ai.key,
+                s.allow_program_winners,
+            )?;
+
+            // Nothing to send when the pool only covered commission; BonusSent means money moved
+            if bonus_each > 0 {
+                pda_pay(&state_ai, &win_ai.to_account_info(), bonus_each)?;
+
+                emit!(BonusSent {
+                    challenge_id: s.challenge_id,
+                    subscriber: *winner,
+                    timestamp: now,
+                    amount: bonus_each,
+                    seq,
+                });
+            }
+        }
+
+        // Record who got paid so the next chunk can't overlap
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.bonus_per_winner = bonus_each;
+        s_mut.chunk_leftover = leftover;
+        s_mut.paid_winners.extend(chunk);
+        s_mut.bump_op_counter(count as u64);
+
+        // Last chunk: route dust per the leftover policy, latch and close
+        if end == winners_len {
+            pay_leftover(
+                s_mut.leftover_policy,
+                &state_ai,
+                &treasury_ai,
+                ctx.accounts.owner_wallet.as_ref().map(|a| a.as_ref()),
+                leftover,
+            )?;
+
+            s_mut.paid = true;
+            s_mut.status = 2;  // 2 = CLOSED
+            s_mut.distribution_nonce = s_mut.distribution_nonce.saturating_add(1);
+            s_mut.bump_op_counter(1);
+
+            // Amounts are this chunk's transfers; earlier chunks reported theirs in BonusSent
+            emit!(ChallengeClosed {
+                challenge_id: s_mut.challenge_id,
+                timestamp: now,
+                final_commission_paid: commission_paid,
+                final_prizes_paid: bonus_each * count as u64,
+                subscribers_at_close: s_mut.subscribers.len() as u32,
+                winners_at_close: winners_len as u32,
+                seq,
+            });
+        }
+
+        // Everything this chunk sent out (commission, prizes, leftover)
+        let moved = balance_before.saturating_sub(state_ai.lamports());
+        s_mut.total_distributed = s_mut.total_distributed.saturating_add(moved);
+        s_mut.in_distribution = false;
+
+        Ok(())
+    }
+
+    // Pay each winner their stored fixed amount (set_winners_with_amounts); commission is taken
+    // from available as usual and whatever remains follows the leftover policy.
+    // Winner wallets come first in remaining_accounts in winners_list order, split treasury wallets after.
+    pub fn distribute_fixed<'info>(
+        ctx: Context<'_, '_, '_, 'info, SendBonus<'info>>,
+        expected_nonce: u64,
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let now = current_timestamp()?;
+        validate_distributor(&ctx.accounts.owner.key(), &ctx.accounts.state)?;
+        require_current_version(&ctx.accounts.state.to_account_info())?;
+        require!(!ctx.accounts.state.distribution_frozen, internal::ErrorCode::DistributionFrozen);
+
+        // Same defense-in-depth guard as send_bonus_to_winners
+        require!(!ctx.accounts.state.in_distribution, internal::ErrorCode::InvalidStatus);
+        ctx.accounts.state.in_distribution = true;
+        ctx.accounts.state.canonicalize_winners();
+
+        let s = &ctx.accounts.state;
+
+        // Same guards as send_bonus_to_winners
+        require!(s.distribution_nonce == expected_nonce, internal::ErrorCode::InvalidNonce);
+        require!(!s.paid, internal::ErrorCode::InvalidStatus);
+        require!(s.paid_winners.is_empty(), internal::ErrorCode::InvalidStatus);
+        require!(s.status != 3, internal::ErrorCode::InvalidStatus); // 3 = CANCELED
+        require!(
+            ctx.accounts.treasury_wallet.key() == s.treasury,
+            internal::ErrorCode::InvalidInput
+        );
+        require!(!s.winner_amounts.is_empty(), internal::ErrorCode::NoWinnersConfigured);
+
+        let winners_len = s.winners_list.len();
+        let split_len = s.treasuries.len();
+        require!(
+            ctx.remaining_accounts.len() == winners_len + split_len,
+            internal::ErrorCode::MissingWinnerAccount
+        );
+
+        let state_ai = ctx.accounts.state.to_account_info();
+        let treasury_ai = ctx.accounts.treasury_wallet.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(state_ai.data_len());
+        let total = state_ai.lamports();
+        let available = total
+            .checked_sub(rent_exempt)
+            .ok_or(error!(internal::ErrorCode::InsufficientContractBalance))?;
+
+        // Commission plus every fixed prize must fit in what's available
+        let BonusBreakdown { commission_amount: commission, .. } = {
+            let (num, den) = s.commission_fraction();
+            calculate_competition_bonus(available, num, den, 0, false)?
+        };
+        let prizes = s
+            .winner_amounts
+            .iter()
+            .try_fold(0u64, |acc, a| acc.checked_add(*a))
+            .ok_or(error!(internal::ErrorCode::LamportMathError))?;
+        let leftover = available
+            .checked_sub(commission)
+            .and_then(|v| v.checked_sub(prizes))
+            .ok_or(error!(internal::ErrorCode::InsufficientContractBalance))?;
+
+        pay_commission(
+            &state_ai,
+            &treasury_ai,
+            &s.treasuries,
+            &ctx.remaining_accounts[winners_len..],
+            commission,
+        )?;
+
+        for (i, winner) in s.winners_list.iter().enumerate() {
+            let win_ai = &ctx.remaining_accounts[i];
+            validate_winner_account(
+                win_ai,
+                winner,
+                state_ai.key,
+                treasury_ai.key,
+                s.allow_program_winners,
+            )?;
+
+            let amount = s.winner_amounts[i];
+            pda_pay(&state_ai, &win_ai.to_account_info(), amount)?;
+
+            emit!(BonusSent {
+                challenge_id: s.challenge_id,
+                subscriber: *winner,
+                timestamp: now,
+                amount,
+                seq,
+            });
+        }
+
+        pay_leftover(
+            s.leftover_policy,
+            &state_ai,
+            &treasury_ai,
+            ctx.accounts.owner_wallet.as_ref().map(|a| a.as_ref()),
+            leftover,
+        )?;
+
+        // Latch and close; payout done, so release the reentrancy guard
+        let moved = total.saturating_sub(state_ai.lamports());
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.in_distribution = false;
+        s_mut.paid = true;
+        s_mut.status = 2;  // 2 = CLOSED
+        s_mut.distribution_nonce = s_mut.distribution_nonce.saturating_add(1);
+        s_mut.bump_op_counter(1 + winners_len as u64);
+        s_mut.total_distributed = s_mut.total_distributed.saturating_add(moved);
+
+        // Lamports that actually left the PDA; a leftover kept under policy 2 stays out
+        emit!(DistributionSummary {
+            challenge_id: s_mut.challenge_id,
+            total_distributed: moved,
+            commission,
+            winners_paid: winners_len as u32,
+            leftover,
+            timestamp: now,
+            leftover_policy: s_mut.leftover_policy,
+            seq,
+        });
+
+        emit!(ChallengeClosed {
+            challenge_id: s_mut.challenge_id,
+            timestamp: now,
+            final_commission_paid: commission,
+            final_prizes_paid: prizes,
+            subscribers_at_close: s_mut.subscribers.len() as u32,
+            winners_at_close: winners_len as u32,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    // refund(address[] subscribers) — batch refund
+    pub fn refund_batch<'info>(ctx: Context<'_, '_, '_, 'info, RefundBatch<'info>>, subscribers: Vec<Pubkey>) -> Result<()> {
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let now = current_timestamp()?;
+        let s = &ctx.accounts.state;
+        
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        // Sponsor money is never refundable
+        let refundable = s.refundable_balance(total, rent_exempt);
+
+        // Refund what each one actually paid, within each one's refund window
+        let mut need: u64 = 0;
+        for sub in subscribers.iter() {
+            // Only current subscribers - never pay out of sponsor money
+            let idx = s
+                .subscriber_index(sub)
+                .map_err(|_| error!(internal::ErrorCode::InvalidSubscriberAddress))?;
+            s.check_refund_window(s.subscribers[idx].joined_at, now)?;
+            let paid = s.subscribers[idx].paid;
+            need = need
+                .checked_add(paid)
+                .ok_or(error!(internal::ErrorCode::InvalidInput))?;
+        }
+
+        require!(refundable >= need, internal::ErrorCode::InsufficientContractBalance);
+        s.check_refund_budget(need)?;
+
+        require!(
+            ctx.remaining_accounts.len() == subscribers.len(),
+            internal::ErrorCode::MissingSubscriberAccount
+        );
+
+        let balance_before = total;
+        let mut deducted: u64 = 0;
+        let mut sent: u64 = 0;
+        for (i, sub) in subscribers.iter().enumerate() {
+            require!(*sub != Pubkey::default(), internal::ErrorCode::InvalidSubscriberAddress);
+            // Once each
+            require!(!subscribers[..i].contains(sub), internal::ErrorCode::InvalidSubscriberAddress);
+
+            let sub_ai = ctx.remaining_accounts.get(i).unwrap();
+            require!(*sub_ai.key == *sub, internal::ErrorCode::InvalidSubscriberAddress);
+            require!(
+                sub_ai.owner == &system_program::ID,
+                internal::ErrorCode::InvalidSubscriberAddress
+            );
+
+            // Subscriber gets what they paid minus any processing fee
+            let paid = s.paid_by(sub).unwrap_or(0);
+            let amount = paid.saturating_sub(s.refund_fee);
+            deducted += paid - amount;
+            sent += amount;
+            if amount > 0 {
+                pda_pay(
+                    &ctx.accounts.state.to_account_info(),
+                    &sub_ai.to_account_info(),
+                    amount,
+                )?;
+            }
+
+            emit!(RefundSent {
+                challenge_id: s.challenge_id,
+                subscriber: *sub,
+                timestamp: now,
+                amount,
+                seq,
+            });
+        }
+
+        // Processing fees go to treasury in one transfer
+        if deducted > 0 {
+            pda_pay(
+                &ctx.accounts.state.to_account_info(),
+                &ctx.accounts.treasury_wallet.to_account_info(),
+                deducted,
+            )?;
+        }
+        let balance_after = ctx.accounts.state.to_account_info().lamports();
+
+        // Remove refunded subscribers from the list 
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.subscribers.retain(|e| !subscribers.contains(&e.who));
+        s_mut.total_refunded = s_mut.total_refunded.saturating_add(need);
+        s_mut.bump_op_counter(1 + subscribers.len() as u64);
+
+        // One reconciliation line for the whole batch; total_refunded is net of processing fees
+        emit!(RefundBatchSummary {
+            challenge_id: s_mut.challenge_id,
+            count: subscribers.len() as u32,
+            total_refunded: sent,
+            balance_before,
+            balance_after,
+            seq,
         });
 
-        emit!(CommisionAndBonusCalculated {
-            commission,
-            bonus: bonus_each,
-            timestamp: Clock::get()?.unix_timestamp
-        });
+        Ok(())
+    }
+
+    // Refund every stored subscriber; wallets in remaining_accounts follow state.subscribers order
+    pub fn refund_all<'info>(ctx: Context<'_, '_, '_, 'info, RefundBatch<'info>>) -> Result<()> {
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        refund_every_subscriber(ctx, seq, current_timestamp()?)
+    }
+
+    // Refund state.subscribers[start..start+count] so a large list can be drained over several
+    // transactions. The refunded slice is removed, so the list itself is the progress marker:
+    // calling with start = 0 until it is empty refunds everyone.
+    pub fn refund_range<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefundBatch<'info>>,
+        start: u32,
+        count: u32,
+    ) -> Result<()> {
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let now = current_timestamp()?;
+        let s = &ctx.accounts.state;
+
+        let start = start as usize;
+        let end = start
+            .checked_add(count as usize)
+            .ok_or(error!(internal::ErrorCode::InvalidInput))?;
+        require!(count > 0, internal::ErrorCode::InvalidInput);
+        require!(end <= s.subscribers.len(), internal::ErrorCode::InvalidInput);
+
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        // Sponsor money is never refundable
+        let refundable = s.refundable_balance(total, rent_exempt);
+
+        // Per-subscriber path, so each entry's refund window applies
+        for e in s.subscribers[start..end].iter() {
+            s.check_refund_window(e.joined_at, now)?;
+        }
+        let need = s.subscribers[start..end]
+            .iter()
+            .try_fold(0u64, |acc, e| acc.checked_add(e.paid))
+            .ok_or(error!(internal::ErrorCode::InvalidInput))?;
+
+        require!(refundable >= need, internal::ErrorCode::InsufficientContractBalance);
+        s.check_refund_budget(need)?;
+
+        // One wallet per subscriber in the slice, same order
+        require!(
+            ctx.remaining_accounts.len() == count as usize,
+            internal::ErrorCode::MissingSubscriberAccount
+        );
+
+        let mut deducted: u64 = 0;
+        for (i, e) in s.subscribers[start..end].iter().enumerate() {
+            let sub_ai = ctx.remaining_accounts.get(i).unwrap();
+            require!(*sub_ai.key == e.who, internal::ErrorCode::InvalidSubscriberAddress);
+            require!(
+                sub_ai.owner == &system_program::ID,
+                internal::ErrorCode::InvalidSubscriberAddress
+            );
+
+            // Subscriber gets what they paid minus any processing fee
+            let amount = e.paid.saturating_sub(s.refund_fee);
+            deducted += e.paid - amount;
+            if amount > 0 {
+                pda_pay(
+                    &ctx.accounts.state.to_account_info(),
+                    &sub_ai.to_account_info(),
+                    amount,
+                )?;
+            }
+
+            emit!(RefundSent {
+                challenge_id: s.challenge_id,
+                subscriber: e.who,
+                timestamp: now,
+                amount,
+                seq,
+            });
+        }
+
+        // Processing fees go to treasury in one transfer
+        if deducted > 0 {
+            pda_pay(
+                &ctx.accounts.state.to_account_info(),
+                &ctx.accounts.treasury_wallet.to_account_info(),
+                deducted,
+            )?;
+        }
+
+        // Drop only the refunded slice
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.subscribers.drain(start..end);
+        s_mut.total_refunded = s_mut.total_refunded.saturating_add(need);
+        s_mut.bump_op_counter(1 + count as u64);
+
+        Ok(())
+    }
+
+    // Refund one subscriber to a different wallet (lost-key recovery); primary owner only
+    pub fn refund_to(ctx: Context<RefundTo>, subscriber: Pubkey, destination: Pubkey) -> Result<()> {
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &ctx.accounts.state;
+
+        require!(
+            ctx.accounts.destination.key() == destination,
+            internal::ErrorCode::InvalidInput
+        );
+        let idx = s
+            .subscriber_index(&subscriber)
+            .map_err(|_| error!(internal::ErrorCode::InvalidSubscriberAddress))?;
+        let paid = s.subscribers[idx].paid;
+        s.check_refund_window(s.subscribers[idx].joined_at, current_timestamp()?)?;
+
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        // Sponsor money is never refundable
+        let refundable = s.refundable_balance(total, rent_exempt);
+        require!(refundable >= paid, internal::ErrorCode::InsufficientContractBalance);
+        s.check_refund_budget(paid)?;
+
+        // Same processing fee as any other admin refund
+        let amount = paid.saturating_sub(s.refund_fee);
+        let deducted = paid - amount;
+        let challenge_id = s.challenge_id;
 
-        // Send company commission to treasury wallet
-        if commission > 0 {
+        if amount > 0 {
+            pda_pay(
+                &ctx.accounts.state.to_account_info(),
+                &ctx.accounts.destination.to_account_info(),
+                amount,
+            )?;
+        }
+        if deducted > 0 {
             pda_pay(
                 &ctx.accounts.state.to_account_info(),
                 &ctx.accounts.treasury_wallet.to_account_info(),
-                commission,
+                deducted,
             )?;
         }
 
-        // Validate remaining accounts alignment
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.subscribers.remove(idx);
+        s_mut.total_refunded = s_mut.total_refunded.saturating_add(paid);
+        s_mut.bump_op_counter(1);
+
+        emit!(RefundRedirected {
+            challenge_id,
+            subscriber,
+            destination,
+            amount,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Cancel and refund everyone in one go; wallets follow state.subscribers order
+    pub fn cancel_and_refund<'info>(ctx: Context<'_, '_, '_, 'info, RefundBatch<'info>>) -> Result<()> {
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+
+        // Can't cancel after payout, and CLOSED is terminal
+        require!(!s.paid, internal::ErrorCode::InvalidStatus);
+        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+
+        let challenge_id = s.challenge_id;
+        let now = current_timestamp()?;
+        s.mark_canceled(now);
+        let seq = s.next_event_seq();
+
+        // Same refund path as refund_all, under this instruction's sequence number and clock
+        refund_every_subscriber(ctx, seq, now)?;
+
+        emit!(ChallengeCanceled { challenge_id, seq });
+        Ok(())
+    }
+
+    // Escape hatch: any subscriber can refund everyone once the deadline passes unpaid.
+    // Every wallet rides in one transaction, so large challenges use force_refund_range instead.
+    pub fn force_refund_all<'info>(ctx: Context<'_, '_, '_, 'info, ForceRefundAll<'info>>) -> Result<()> {
+        let count = ctx.accounts.state.subscribers.len() as u32;
+        force_refund_range(ctx, 0, count)
+    }
+
+    // Chunked escape hatch: refund state.subscribers[start..start+count] once the deadline
+    // passes unpaid. Any current subscriber can call it, the refunded slice is removed (call with
+    // start = 0 until the list is empty), and the first call cancels a challenge that isn't
+    // already terminal (a CLOSED one stays CLOSED).
+    // Everyone gets what they paid; if the pool fell short, a pro-rata cut of what is left. The
+    // ratio is recomputed per slice and shares round down, so later slices never get less.
+    // Not subject to the refund budget: the owner must not be able to block the escape hatch.
+    pub fn force_refund_range<'info>(
+        ctx: Context<'_, '_, '_, 'info, ForceRefundAll<'info>>,
+        start: u32,
+        count: u32,
+    ) -> Result<()> {
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let now = current_timestamp()?;
+        let s = &ctx.accounts.state;
+
+        // Only before distribution and only once the deadline is set and passed
+        require!(!s.paid, internal::ErrorCode::InvalidStatus);
+        require!(s.distribution_deadline != 0, internal::ErrorCode::DeadlineNotReached);
         require!(
-            ctx.remaining_accounts.len() == winners_len as usize,
-            internal::ErrorCode::MissingWinnerAccount
+            now > s.distribution_deadline,
+            internal::ErrorCode::DeadlineNotReached
         );
 
-        // Send prize money to each winner
-        if bonus_each > 0 {
-            for (i, winner) in s.winners_list.iter().enumerate() {
-                // Make sure winner address is valid
-                require!(*winner != Pubkey::default(), internal::ErrorCode::InvalidWinnerAddress);
+        // Caller must be one of the remaining subscribers
+        require!(
+            s.is_subscriber(&ctx.accounts.caller.key()),
+            internal::ErrorCode::Unauthorized
+        );
 
-                // Get winner's wallet from the accounts passed in
-                let win_ai = ctx.remaining_accounts.get(i).unwrap();
-                // Make sure the wallet matches the winner address
-                require!(*win_ai.key == *winner, internal::ErrorCode::InvalidWinnerAddress);
-                // Make sure it's a regular Solana account
-                require!(
-                    win_ai.owner == &system_program::ID,
-                    internal::ErrorCode::InvalidWinnerAddress
-                );
+        let start = start as usize;
+        let end = start
+            .checked_add(count as usize)
+            .ok_or(error!(internal::ErrorCode::InvalidInput))?;
+        require!(count > 0, internal::ErrorCode::InvalidInput);
+        require!(end <= s.subscribers.len(), internal::ErrorCode::InvalidInput);
 
-                // Send prize money to winner
+        // One wallet per subscriber in the slice, same order
+        require!(
+            ctx.remaining_accounts.len() == count as usize,
+            internal::ErrorCode::MissingSubscriberAccount
+        );
+
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        // Sponsor money is never refundable
+        let refundable = s.refundable_balance(total, rent_exempt);
+        let total_paid = s.total_paid()?;
+
+        let mut refunded: u64 = 0;
+        for (i, e) in s.subscribers[start..end].iter().enumerate() {
+            let sub_ai = ctx.remaining_accounts.get(i).unwrap();
+            require!(*sub_ai.key == e.who, internal::ErrorCode::InvalidSubscriberAddress);
+            require!(
+                sub_ai.owner == &system_program::ID,
+                internal::ErrorCode::InvalidSubscriberAddress
+            );
+
+            let share = if refundable >= total_paid {
+                e.paid
+            } else {
+                (e.paid as u128 * refundable as u128 / total_paid as u128) as u64
+            };
+            if share > 0 {
                 pda_pay(
                     &ctx.accounts.state.to_account_info(),
-                    &win_ai.to_account_info(),
-                    bonus_each,
+                    &sub_ai.to_account_info(),
+                    share,
                 )?;
-
-                // Tell this winner got paid
-                emit!(BonusSent {
-                    challenge_id: s.challenge_id,
-                    subscriber: *winner,
-                    timestamp: Clock::get()?.unix_timestamp
-                });
             }
+            refunded += share;
+
+            emit!(RefundSent {
+                challenge_id: s.challenge_id,
+                subscriber: e.who,
+                timestamp: now,
+                amount: share,
+                seq,
+            });
         }
 
-        // Send commission to treasury
-        if leftover > 0 {
-            pda_pay(
-                &ctx.accounts.state.to_account_info(),
-                &ctx.accounts.treasury_wallet.to_account_info(),
-                leftover,
-            )?;
+        // The challenge can no longer pay out; drop only the refunded slice
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.subscribers.drain(start..end);
+        if !is_terminal_status(s_mut.status) {
+            s_mut.mark_canceled(now);
         }
+        s_mut.total_refunded = s_mut.total_refunded.saturating_add(refunded);
+        s_mut.bump_op_counter(1 + count as u64);
+
+        Ok(())
+    }
+
+    // Send sponsor deposits to treasury once a challenge is canceled
+    pub fn reclaim_sponsor_funds(ctx: Context<ReclaimSponsorFunds>) -> Result<()> {
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &ctx.accounts.state;
+
+        require!(s.status == 3, internal::ErrorCode::InvalidStatus); // 3 = CANCELED
+        require!(s.sponsored_total > 0, internal::ErrorCode::InvalidAmount);
+        require!(
+            ctx.accounts.treasury_wallet.key() == s.treasury,
+            internal::ErrorCode::InvalidInput
+        );
+
+        // Never dip below rent
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        let amount = s.sponsored_total.min(total.saturating_sub(rent_exempt));
+        require!(amount > 0, internal::ErrorCode::InsufficientContractBalance);
+
+        pda_pay(
+            &ctx.accounts.state.to_account_info(),
+            &ctx.accounts.treasury_wallet.to_account_info(),
+            amount,
+        )?;
 
-        // Latch and close
         let s_mut = &mut ctx.accounts.state;
-        s_mut.paid = true;
-        s_mut.status = 2;  // 2 = CLOSED
-        s_mut.op_counter = s_mut.op_counter.saturating_add(1 + winners_len);
+        s_mut.sponsored_total -= amount;
+        s_mut.bump_op_counter(1);
 
-        // Emit terminal event for clean archival
-        emit!(ChallengeClosed {
+        emit!(SponsorFundsReclaimed {
             challenge_id: s_mut.challenge_id,
-            timestamp: Clock::get()?.unix_timestamp,
+            amount,
+            seq,
         });
 
         Ok(())
     }
 
-    // refund(address[] subscribers) — batch refund
-    pub fn refund_batch<'info>(ctx: Context<'_, '_, '_, 'info, RefundBatch<'info>>, subscribers: Vec<Pubkey>) -> Result<()> {
+    // Sweep lamports sent straight to the PDA (anything above rent + fees + sponsor money)
+    pub fn withdraw_excess(ctx: Context<WithdrawExcess>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
         let s = &ctx.accounts.state;
-        
-        let rent_exempt = Rent::get()?.minimum_balance(State::MAX_SIZE);
+        require!(
+            ctx.accounts.treasury_wallet.key() == s.treasury,
+            internal::ErrorCode::InvalidInput
+        );
+
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
         let total = **ctx.accounts.state.to_account_info().lamports.borrow();
-        let available = total.saturating_sub(rent_exempt);
 
-        let need = s
-            .fee
-            .checked_mul(subscribers.len() as u64)
-            .ok_or(error!(internal::ErrorCode::InvalidInput))?;
+        // What legitimately belongs to subscribers and sponsors (nothing once paid out)
+        let expected_pool = if s.paid {
+            0
+        } else {
+            s.total_paid()?
+                .checked_add(s.sponsored_total)
+                .ok_or(error!(internal::ErrorCode::LamportMathError))?
+        };
 
-        require!(available >= need, internal::ErrorCode::InsufficientContractBalance);
+        let amount = total.saturating_sub(rent_exempt).saturating_sub(expected_pool);
+        require!(amount > 0, internal::ErrorCode::InvalidAmount);
 
-        require!(
-            ctx.remaining_accounts.len() == subscribers.len(),
-            internal::ErrorCode::MissingSubscriberAccount
-        );
+        pda_pay(
+            &ctx.accounts.state.to_account_info(),
+            &ctx.accounts.treasury_wallet.to_account_info(),
+            amount,
+        )?;
 
-        for (i, sub) in subscribers.iter().enumerate() {
-            require!(*sub != Pubkey::default(), internal::ErrorCode::InvalidSubscriberAddress);
+        let s_mut = &mut ctx.accounts.state;
+        s_mut.bump_op_counter(1);
 
-            let sub_ai = ctx.remaining_accounts.get(i).unwrap();
-            require!(*sub_ai.key == *sub, internal::ErrorCode::InvalidSubscriberAddress);
+        emit!(ExcessWithdrawn { amount, seq });
+        Ok(())
+    }
+
+    // When unclaimed refunds of a canceled challenge may be swept (0 = never). The deadline is
+    // at least MIN_UNCLAIMED_GRACE_SECS after the cancel, and after the cancel it can only move later.
+    // Still allowed after emergency_stop: it is the only way a stopped challenge ever gets swept.
+    pub fn set_unclaimed_deadline(ctx: Context<OnlyOwner>, unclaimed_deadline: i64) -> Result<()> {
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        require!(!s.paid, internal::ErrorCode::InvalidStatus);
+        require!(
+            unclaimed_deadline == 0 || unclaimed_deadline > current_timestamp()?,
+            internal::ErrorCode::InvalidInput
+        );
+        // Once canceled, subscribers are owed the grace period and the deadline only moves later
+        if s.status == 3 {
             require!(
-                sub_ai.owner == &system_program::ID,
-                internal::ErrorCode::InvalidSubscriberAddress
+                s.sweep_allowed_deadline(unclaimed_deadline),
+                internal::ErrorCode::UnclaimedGraceNotMet
+            );
+            require!(
+                unclaimed_deadline >= s.unclaimed_deadline,
+                internal::ErrorCode::InvalidInput
             );
+        }
 
+        s.unclaimed_deadline = unclaimed_deadline;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Finality for an abandoned CANCELED challenge: after the unclaimed deadline everything
+    // above rent goes to treasury and the subscriber list is cleared.
+    // after_ts must equal the stored deadline so the caller confirms which deadline they act on.
+    pub fn sweep_unclaimed(ctx: Context<WithdrawExcess>, after_ts: i64) -> Result<()> {
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &ctx.accounts.state;
+        require!(
+            ctx.accounts.treasury_wallet.key() == s.treasury,
+            internal::ErrorCode::InvalidInput
+        );
+
+        require!(s.status == 3, internal::ErrorCode::InvalidStatus); // Only CANCELED
+        require!(s.unclaimed_deadline != 0, internal::ErrorCode::DeadlineNotReached);
+        require!(after_ts == s.unclaimed_deadline, internal::ErrorCode::InvalidInput);
+        // A deadline set before the cancel still has to clear the grace period
+        require!(
+            s.sweep_allowed_deadline(s.unclaimed_deadline),
+            internal::ErrorCode::UnclaimedGraceNotMet
+        );
+        require!(
+            current_timestamp()? > s.unclaimed_deadline,
+            internal::ErrorCode::DeadlineNotReached
+        );
+
+        let rent_exempt = Rent::get()?.minimum_balance(ctx.accounts.state.to_account_info().data_len());
+        let total = **ctx.accounts.state.to_account_info().lamports.borrow();
+        let amount = total.saturating_sub(rent_exempt);
+
+        if amount > 0 {
             pda_pay(
                 &ctx.accounts.state.to_account_info(),
-                &sub_ai.to_account_info(),
-                s.fee,
+                &ctx.accounts.treasury_wallet.to_account_info(),
+                amount,
             )?;
-
-            emit!(RefundSent {
-                challenge_id: s.challenge_id,
-                subscriber: *sub,
-                timestamp: Clock::get()?.unix_timestamp
-            });
         }
 
-        // Remove refunded subscribers from the list 
         let s_mut = &mut ctx.accounts.state;
-        s_mut.subscribers.retain(|pk| !subscribers.contains(pk));
-        s_mut.op_counter = s_mut.op_counter.saturating_add(1 + subscribers.len() as u64);
+        let challenge_id = s_mut.challenge_id;
+        s_mut.subscribers.clear();
+        s_mut.sponsored_total = 0;
+        s_mut.bump_op_counter(1);
+
+        emit!(UnclaimedSwept {
+            challenge_id,
+            amount,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Seed the next round: move amount of a closed, paid challenge's remaining balance into a
+    // sibling challenge (same creator). It lands as sponsor money there, so it goes to the
+    // prize pool and is never refunded. The source always keeps its rent-exempt minimum.
+    pub fn rollover_to(ctx: Context<RolloverTo>, next_state: Pubkey, amount: u64) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        require_not_stopped(&ctx.accounts.next_state_account)?;
+        require_no_chunked_payout(&ctx.accounts.state)?;
+        require_no_chunked_payout(&ctx.accounts.next_state_account)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let from = ctx.accounts.state.key();
+        require!(from != next_state, internal::ErrorCode::InvalidInput);
+        require!(amount > 0, internal::ErrorCode::InvalidAmount);
+
+        // Source: distribution finished
+        let s = &ctx.accounts.state;
+        require!(s.status == 2 && s.paid, internal::ErrorCode::InvalidStatus); // 2 = CLOSED
+        require!(!s.in_distribution, internal::ErrorCode::InvalidStatus);
+        // Destination: still able to pay out
+        let next = &ctx.accounts.next_state_account;
+        require!(!is_terminal_status(next.status), internal::ErrorCode::InvalidStatus);
+        require!(!next.paid, internal::ErrorCode::InvalidStatus);
+
+        let state_ai = ctx.accounts.state.to_account_info();
+        let rent_exempt = Rent::get()?.minimum_balance(state_ai.data_len());
+        let available = state_ai.lamports().saturating_sub(rent_exempt);
+        require!(amount <= available, internal::ErrorCode::InsufficientContractBalance);
+        let next_balance = ctx.accounts.next_state_account.to_account_info().lamports();
+        validate_tvl(&ctx.accounts.next_state_account, next_balance, amount)?;
+
+        pda_pay(&state_ai, &ctx.accounts.next_state_account.to_account_info(), amount)?;
+
+        let next = &mut ctx.accounts.next_state_account;
+        next.sponsored_total = next
+            .sponsored_total
+            .checked_add(amount)
+            .ok_or(error!(internal::ErrorCode::LamportMathError))?;
+        next.bump_op_counter(1);
+        let s = &mut ctx.accounts.state;
+        s.bump_op_counter(1);
+
+        emit!(RolledOver {
+            challenge_id: s.challenge_id,
+            from,
+            to: next_state,
+            amount,
+            seq,
+        });
+        Ok(())
+    }
+
+    // getOperationFee() 
+    pub fn get_operation_fee(ctx: Context<OnlyOwner>) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        emit!(OperationFeeRead { value: ctx.accounts.state.op_counter, seq });
+        Ok(())
+    }
+
+    // Timeline marker for off-chain tooling: the op_counter next to the current slot and time.
+    // Read-only like get_operation_fee, so it takes no event sequence number of its own.
+    pub fn checkpoint(ctx: Context<OnlyOwner>) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        let clock = current_clock()?;
+        emit!(OpCheckpoint {
+            challenge_id: ctx.accounts.state.challenge_id,
+            op_counter: ctx.accounts.state.op_counter,
+            slot: clock.slot,
+            timestamp: clock.unix_timestamp,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Housekeeping: zero the display counter (no validation reads op_counter)
+    pub fn reset_op_counter(ctx: Context<OnlyOwner>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+        let previous = s.op_counter;
+        s.op_counter = 0;
+        emit!(OpCounterReset {
+            challenge_id: s.challenge_id,
+            previous,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Paginated subscriber read for indexers; limit is clamped to keep the event small
+    pub fn get_subscribers_page(ctx: Context<ReadState>, offset: u32, limit: u32) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        let s = &ctx.accounts.state;
+        let start = offset as usize;
+        require!(start <= s.subscribers.len(), internal::ErrorCode::InvalidInput);
+
+        let limit = (limit as usize).min(State::MAX_PAGE_SIZE);
+        let end = start.saturating_add(limit).min(s.subscribers.len());
+
+        emit!(SubscribersPage {
+            challenge_id: s.challenge_id,
+            offset,
+            subscribers: s.subscribers[start..end].iter().map(|e| e.who).collect(),
+            seq,
+        });
+        Ok(())
+    }
+
+    // Whole winners list in stored (payout) order, so clients can build remaining_accounts.
+    // amounts is empty unless fixed amounts were set; winner_capacity keeps this one event.
+    pub fn get_winners(ctx: Context<ReadState>) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        let s = &ctx.accounts.state;
+        emit!(WinnersList {
+            challenge_id: s.challenge_id,
+            winners: s.winners_list.clone(),
+            amounts: s.winner_amounts.clone(),
+            seq,
+        });
+        Ok(())
+    }
+
+    // Re-anchor the off-chain rules; only before signups close
+    pub fn update_metadata_hash(ctx: Context<OnlyOwner>, metadata_hash: [u8; 32]) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+
+        // Rules are frozen once the challenge leaves PENDING
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+
+        s.metadata_hash = metadata_hash;
+        s.bump_op_counter(1);
+
+        emit!(MetadataUpdated {
+            challenge_id: s.challenge_id,
+            metadata_hash,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Allow treasury rotation by owner; the new wallet is passed in so Anchor checks it's
+    // system-owned now instead of payouts failing on a program account later
+    pub fn set_treasury(ctx: Context<SetTreasury>, new_treasury: Pubkey) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        validate_treasury(s, &new_treasury)?;
+        s.treasury = new_treasury;
+        // Rotating the single treasury drops any commission split
+        s.treasuries.clear();
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Split commission across several treasuries; bps must sum to 10000.
+    // The first wallet becomes state.treasury and also receives leftovers
+    pub fn set_treasuries(ctx: Context<OnlyOwner>, splits: Vec<TreasurySplit>) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+
+        // No payout config changes after close
+        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+
+        require!(
+            !splits.is_empty() && splits.len() <= State::MAX_TREASURIES,
+            internal::ErrorCode::InvalidInput
+        );
+
+        let mut total_bps: u32 = 0;
+        for (i, split) in splits.iter().enumerate() {
+            validate_treasury(s, &split.wallet)?;
+            require!(
+                !splits[..i].iter().any(|t| t.wallet == split.wallet),
+                internal::ErrorCode::InvalidInput
+            );
+            total_bps += split.bps as u32;
+        }
+        require!(
+            total_bps == State::BPS_DENOMINATOR as u32,
+            internal::ErrorCode::InvalidCommissionRate
+        );
+
+        s.treasury = splits[0].wallet;
+        s.treasuries = splits;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // Wallets send_bonus_to_winners may route commission to instead of state.treasury
+    pub fn set_approved_commission_recipients(
+        ctx: Context<OnlyOwner>,
+        recipients: Vec<Pubkey>,
+    ) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+
+        // No payout config changes after close
+        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+        require!(
+            recipients.len() <= State::MAX_APPROVED_RECIPIENTS,
+            internal::ErrorCode::InvalidInput
+        );
+
+        for (i, r) in recipients.iter().enumerate() {
+            // Same rules as the treasury itself
+            validate_treasury(s, r)?;
+            require!(!recipients[..i].contains(r), internal::ErrorCode::InvalidInput);
+        }
+
+        s.approved_commission_recipients = recipients;
+        s.bump_op_counter(1);
+        Ok(())
+    }
+
+    // setCommision(uint8) 
+    pub fn set_commision(ctx: Context<UpdateCommission>, commission_percentage: u8) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        validate_primary_owner(&ctx.accounts.owner.key(), &ctx.accounts.state.owner)?;
+        require_current_version(&ctx.accounts.state.to_account_info())?;
+        require!(commission_percentage <= 100, internal::ErrorCode::InvalidCommissionRate);
+        
+        // Only allow changes while PENDING
+        require!(ctx.accounts.state.status == 0, internal::ErrorCode::InvalidStatus);
+
+        // Economics are frozen once the first person has paid
+        require!(ctx.accounts.state.subscribers.is_empty(), internal::ErrorCode::InvalidStatus);
+        
+        // Whole percent maps straight onto basis points
+        let old_bps = ctx.accounts.state.effective_commission_bps();
+        ctx.accounts.state.set_commission_fraction(commission_percentage as u64, 100);
+        ctx.accounts.state.bump_op_counter(1);
+
+        emit!(CommissionUpdated {
+            challenge_id: ctx.accounts.state.challenge_id,
+            old_bps,
+            new_bps: ctx.accounts.state.commission_bps,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Fractional commission, e.g. 250 = 2.5%
+    pub fn set_commission_bps(ctx: Context<UpdateCommission>, commission_bps: u16) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        validate_primary_owner(&ctx.accounts.owner.key(), &ctx.accounts.state.owner)?;
+        require_current_version(&ctx.accounts.state.to_account_info())?;
+        require!(
+            commission_bps <= State::BPS_DENOMINATOR,
+            internal::ErrorCode::InvalidCommissionRate
+        );
+
+        // Only allow changes while PENDING
+        require!(ctx.accounts.state.status == 0, internal::ErrorCode::InvalidStatus);
 
-        Ok(())
-    }
+        // Economics are frozen once the first person has paid
+        require!(ctx.accounts.state.subscribers.is_empty(), internal::ErrorCode::InvalidStatus);
 
-    // getOperationFee() 
-    pub fn get_operation_fee(ctx: Context<OnlyOwner>) -> Result<()> {
-        emit!(OperationFeeRead { value: ctx.accounts.state.op_counter });
-        Ok(())
-    }
+        let old_bps = ctx.accounts.state.effective_commission_bps();
+        ctx.accounts.state.set_commission_bps(commission_bps);
+        ctx.accounts.state.bump_op_counter(1);
 
-    // Allow treasury rotation by owner
-    pub fn set_treasury(ctx: Context<OnlyOwner>, new_treasury: Pubkey) -> Result<()> {
-        require!(new_treasury != Pubkey::default(), internal::ErrorCode::InvalidInput);
-        let s = &mut ctx.accounts.state;
-        s.treasury = new_treasury;
-        s.op_counter = s.op_counter.saturating_add(1);
+        emit!(CommissionUpdated {
+            challenge_id: ctx.accounts.state.challenge_id,
+            old_bps,
+            new_bps: ctx.accounts.state.commission_bps,
+            seq,
+        });
         Ok(())
     }
 
-    // setCommision(uint8) 
-    pub fn set_commision(ctx: Context<UpdateCommission>, commission_percentage: u8) -> Result<()> {
+    // Arbitrary fraction, e.g. 1/3; CommissionUpdated reports the rounded-down bps
+    pub fn set_commission_fraction(ctx: Context<UpdateCommission>, num: u64, den: u64) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
         validate_primary_owner(&ctx.accounts.owner.key(), &ctx.accounts.state.owner)?;
-        require!(commission_percentage <= 100, internal::ErrorCode::InvalidCommissionRate);
-        
+        require_current_version(&ctx.accounts.state.to_account_info())?;
+        require!(den > 0 && num <= den, internal::ErrorCode::InvalidCommissionRate);
+
         // Only allow changes while PENDING
         require!(ctx.accounts.state.status == 0, internal::ErrorCode::InvalidStatus);
-        
-        ctx.accounts.state.commission = commission_percentage;
-        ctx.accounts.state.op_counter = ctx.accounts.state.op_counter.saturating_add(1);
+
+        // Economics are frozen once the first person has paid
+        require!(ctx.accounts.state.subscribers.is_empty(), internal::ErrorCode::InvalidStatus);
+
+        let old_bps = ctx.accounts.state.effective_commission_bps();
+        ctx.accounts.state.set_commission_fraction(num, den);
+        ctx.accounts.state.bump_op_counter(1);
+
+        emit!(CommissionUpdated {
+            challenge_id: ctx.accounts.state.challenge_id,
+            old_bps,
+            new_bps: ctx.accounts.state.commission_bps,
+            seq,
+        });
         Ok(())
     }
 
     // setFee(uint256) 
     pub fn set_fee(ctx: Context<UpdateFee>, fee: u64) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
         validate_primary_owner(&ctx.accounts.owner.key(), &ctx.accounts.state.owner)?;
+        require_current_version(&ctx.accounts.state.to_account_info())?;
         require!(fee > 0, internal::ErrorCode::InvalidAmount);
+        validate_fee_bounds(fee, ctx.accounts.state.min_fee, ctx.accounts.state.max_fee)?;
+        // Refund processing fee can never exceed the fee itself
+        require!(fee >= ctx.accounts.state.refund_fee, internal::ErrorCode::InvalidAmount);
         
         // Only allow changes while PENDING
         require!(ctx.accounts.state.status == 0, internal::ErrorCode::InvalidStatus);
+
+        // Economics are frozen once the first person has paid
+        require!(ctx.accounts.state.subscribers.is_empty(), internal::ErrorCode::InvalidStatus);
         
+        let old_fee = ctx.accounts.state.fee;
         ctx.accounts.state.fee = fee;
-        ctx.accounts.state.op_counter = ctx.accounts.state.op_counter.saturating_add(1);
+        ctx.accounts.state.bump_op_counter(1);
+
+        emit!(FeeUpdated {
+            challenge_id: ctx.accounts.state.challenge_id,
+            old_fee,
+            new_fee: fee,
+            seq,
+        });
         Ok(())
     }
 
     // setOwner(address) 
     pub fn set_owner(ctx: Context<OnlyOwner>, new_owner: Pubkey) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
         let s = &mut ctx.accounts.state;
+        s.next_event_seq();
         
         // Only allow owner changes while not CLOSED
         require!(s.status != 2, internal::ErrorCode::InvalidStatus);
@@ -430,11 +3199,201 @@ pub mod snzup_subscription {
         Ok(())
     }
 
+    // Recovery for a paid latch that moved no money. Primary owner only, and only when no payout
+    // ever left the vault (no BonusSent, no commission). expected_nonce must match and is bumped,
+    // so the next send_bonus_to_winners needs the new value and a replayed reopen fails.
+    pub fn reopen_distribution(ctx: Context<OnlyOwner>, expected_nonce: u64) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+
+        require!(s.paid, internal::ErrorCode::InvalidStatus);
+        require!(!s.in_distribution, internal::ErrorCode::InvalidStatus);
+        require!(!s.winners_list.is_empty(), internal::ErrorCode::NoWinnersConfigured);
+        // A v1 payout left no record in total_distributed, so it can never be proven empty
+        require!(!s.legacy_paid, internal::ErrorCode::DistributionNotReopenable);
+        require!(s.distribution_nonce == expected_nonce, internal::ErrorCode::InvalidNonce);
+        require!(
+            s.paid_winners.is_empty() && s.total_distributed == 0,
+            internal::ErrorCode::DistributionNotReopenable
+        );
+
+        let from = s.status;
+        s.paid = false;
+        s.status = 1; // 1 = IN_PROGRESS
+        s.bonus_per_winner = 0;
+        s.distribution_nonce = s.distribution_nonce.saturating_add(1);
+        s.bump_op_counter(1);
+
+        emit!(DistributionReopened {
+            challenge_id: s.challenge_id,
+            from_status: from,
+            nonce: s.distribution_nonce,
+            by: ctx.accounts.owner.key(),
+            seq,
+        });
+        Ok(())
+    }
+
+    // Grant the payout-only role (primary owner only); distributors can't change fees or owners
+    pub fn add_distributor(ctx: Context<OnlyOwner>, distributor: Pubkey) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+        require!(s.status != 2, internal::ErrorCode::InvalidStatus);
+        require!(distributor != Pubkey::default(), internal::ErrorCode::InvalidInput);
+
+        if !s.distributors.contains(&distributor) {
+            require!(
+                s.distributors.len() < State::MAX_DISTRIBUTORS,
+                internal::ErrorCode::TooManyDistributors
+            );
+            s.distributors.push(distributor);
+            s.bump_op_counter(1);
+        }
+        Ok(())
+    }
+
+    // Revoke the payout-only role
+    pub fn remove_distributor(ctx: Context<OnlyOwner>, distributor: Pubkey) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let s = &mut ctx.accounts.state;
+        s.next_event_seq();
+
+        if let Some(i) = s.distributors.iter().position(|x| *x == distributor) {
+            s.distributors.remove(i);
+            s.bump_op_counter(1);
+        }
+        Ok(())
+    }
+
+    // Hand primary control to another wallet. The PDA is derived from state.creator,
+    // so the account address doesn't change with the owner.
+    pub fn transfer_primary_ownership(ctx: Context<OnlyOwner>, new_owner: Pubkey) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        let s = &mut ctx.accounts.state;
+
+        require!(new_owner != Pubkey::default(), internal::ErrorCode::InvalidInput);
+        require!(new_owner != s.owner, internal::ErrorCode::InvalidInput);
+
+        // Keep the owners list in step: old primary out, new primary in
+        let from = s.owner;
+        s.owners.retain(|o| *o != from);
+        if !s.owners.contains(&new_owner) {
+            require!(
+                s.owners.len() < State::MAX_OWNERS,
+                internal::ErrorCode::TooManyOwners
+            );
+            s.owners.push(new_owner);
+        }
+
+        s.owner = new_owner;
+        s.bump_op_counter(1);
+
+        emit!(PrimaryOwnershipTransferred {
+            challenge_id: s.challenge_id,
+            from,
+            to: new_owner,
+            seq,
+        });
+        Ok(())
+    }
+
     // isOwner(address) 
     pub fn is_owner(_ctx: Context<OnlyOwner>) -> Result<()> {
         Ok(())
     }
 
+    // isSubscribed(address) - answer comes back in the event
+    pub fn is_subscribed(ctx: Context<ReadState>, who: Pubkey) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        let s = &ctx.accounts.state;
+        emit!(SubscriptionStatus {
+            challenge_id: s.challenge_id,
+            who,
+            subscribed: s.is_subscriber(&who),
+            seq,
+        });
+        Ok(())
+    }
+
+    // Join time and amount paid for one subscriber - answer comes back in the event
+    pub fn get_subscriber_info(ctx: Context<ReadState>, who: Pubkey) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        let s = &ctx.accounts.state;
+        let entry = s.subscriber_index(&who).ok().map(|i| s.subscribers[i]);
+        emit!(SubscriberInfo {
+            challenge_id: s.challenge_id,
+            who,
+            subscribed: entry.is_some(),
+            paid: entry.map_or(0, |e| e.paid),
+            joined_at: entry.map_or(0, |e| e.joined_at),
+            fully_paid: entry.is_some_and(|e| e.fully_paid),
+            seq,
+        });
+        Ok(())
+    }
+
+    // Lifetime accounting for reporting, independent of the live balance
+    pub fn get_totals(ctx: Context<ReadState>) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        let s = &ctx.accounts.state;
+        emit!(Totals {
+            challenge_id: s.challenge_id,
+            total_collected: s.total_collected,
+            subscribers_count: s.subscribers.len() as u32,
+            sponsored_total: s.sponsored_total,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Has the payout run? winners_paid counts the chunk ledger while a chunked payout is
+    // in flight, else the whole winners list once paid
+    pub fn get_distribution_status(ctx: Context<ReadState>) -> Result<()> {
+        let seq = ctx.accounts.state.event_seq;
+        let s = &ctx.accounts.state;
+        let winners_paid = if !s.paid_winners.is_empty() {
+            s.paid_winners.len()
+        } else if s.paid {
+            s.winners_list.len()
+        } else {
+            0
+        };
+        emit!(DistributionStatus {
+            challenge_id: s.challenge_id,
+            paid: s.paid,
+            status: s.status,
+            winners_paid: winners_paid as u32,
+            seq,
+        });
+        Ok(())
+    }
+
+    // Record the token mint and its decimals for display; payouts stay in SOL for now.
+    // decimals must match the mint account so clients can trust it without fetching the mint.
+    pub fn configure_token(ctx: Context<ConfigureToken>, mint: Pubkey, decimals: u8) -> Result<()> {
+        require_not_stopped(&ctx.accounts.state)?;
+        let seq = ctx.accounts.state.next_event_seq();
+        require!(
+            read_mint_decimals(&ctx.accounts.mint_account.to_account_info())? == decimals,
+            internal::ErrorCode::InvalidMint
+        );
+
+        let s = &mut ctx.accounts.state;
+        // Currency can't change under people who already paid
+        require!(s.status == 0, internal::ErrorCode::InvalidStatus);
+        require!(s.subscribers.is_empty(), internal::ErrorCode::InvalidStatus);
+
+        s.mint = mint;
+        s.decimals = decimals;
+        s.bump_op_counter(1);
+
+        emit!(TokenConfigured { mint, decimals, seq });
+        Ok(())
+    }
+
     // Parity stubs for ERC20 mint getters/setters - Its SOL only - for future
     pub fn get_erc20_mint(_ctx: Context<OnlyOwner>) -> Result<()> {
         Ok(())
@@ -450,14 +3409,16 @@ pub mod snzup_subscription {
 pub struct SubscriptionCreated { 
     pub challenge_id: u64,
     pub subscriber: Pubkey, 
-    pub timestamp: i64 
+    pub timestamp: i64,
+    pub seq: u64,
 }
 
 #[event]
 pub struct SubscriptionCancelled { 
     pub challenge_id: u64,
     pub subscriber: Pubkey, 
-    pub timestamp: i64 
+    pub timestamp: i64,
+    pub seq: u64,
 }
 
 #[event]
@@ -466,7 +3427,8 @@ pub struct CommisionAndBonusCalculation {
     pub balance: u64, 
     #[index] 
     pub challenge_balance: u64, 
-    pub timestamp: i64 
+    pub timestamp: i64,
+    pub seq: u64,
 }
 
 #[event]
@@ -475,14 +3437,17 @@ pub struct CommisionAndBonusCalculated {
     pub commission: u64, 
     #[index] 
     pub bonus: u64, 
-    pub timestamp: i64 
+    pub timestamp: i64,
+    pub seq: u64,
 }
 
 #[event]
 pub struct BonusSent { 
     pub challenge_id: u64,
     pub subscriber: Pubkey, 
-    pub timestamp: i64 
+    pub timestamp: i64,
+    pub amount: u64,
+    pub seq: u64,
 }
 
 #[event]
@@ -490,11 +3455,41 @@ pub struct RefundSent {
     pub challenge_id: u64,
     pub subscriber: Pubkey,
     pub timestamp: i64,
+    pub amount: u64,
+    pub seq: u64,
 }
 
 #[event]
 pub struct OperationFeeRead {
     pub value: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct ExcessWithdrawn {
+    pub amount: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct Migrated {
+    pub from_version: u8,
+    pub to_version: u8,
+    pub seq: u64,
+}
+
+#[event]
+pub struct StateVersion {
+    pub challenge_id: u64,
+    pub version: u8,
+    pub seq: u64,
+}
+
+#[event]
+pub struct OpCounterReset {
+    pub challenge_id: u64,
+    pub previous: u64,
+    pub seq: u64,
 }
 
 #[event]
@@ -503,10 +3498,287 @@ pub struct Initialized {
     pub owner: Pubkey,
     pub treasury: Pubkey,
     pub version: u8,
+    pub category: u8,
+    pub seq: u64,
 }
 
 #[event]
 pub struct ChallengeClosed {
     pub challenge_id: u64,
     pub timestamp: i64,
+    pub final_commission_paid: u64,
+    pub final_prizes_paid: u64,
+    pub subscribers_at_close: u32,
+    pub winners_at_close: u32,
+    pub seq: u64,
+}
+
+#[event]
+pub struct DistributionSummary {
+    pub challenge_id: u64,
+    pub total_distributed: u64,
+    pub commission: u64,
+    pub winners_paid: u32,
+    pub leftover: u64,
+    pub timestamp: i64,
+    pub leftover_policy: u8,
+    pub seq: u64,
+}
+
+#[event]
+pub struct SubscribersPage {
+    pub challenge_id: u64,
+    pub offset: u32,
+    pub subscribers: Vec<Pubkey>,
+    pub seq: u64,
+}
+
+#[event]
+pub struct MetadataUpdated {
+    pub challenge_id: u64,
+    pub metadata_hash: [u8; 32],
+    pub seq: u64,
+}
+
+#[event]
+pub struct SponsorDeposited {
+    pub challenge_id: u64,
+    pub sponsor: Pubkey,
+    pub amount: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct SponsorFundsReclaimed {
+    pub challenge_id: u64,
+    pub amount: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct ChallengeCanceled {
+    pub challenge_id: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct SubscriptionStatus {
+    pub challenge_id: u64,
+    pub who: Pubkey,
+    pub subscribed: bool,
+    pub seq: u64,
+}
+
+#[event]
+pub struct StatusChanged {
+    pub challenge_id: u64,
+    pub from: u8,
+    pub to: u8,
+    pub timestamp: i64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct WinnerRemoved {
+    pub challenge_id: u64,
+    pub winner: Pubkey,
+    pub seq: u64,
+}
+
+#[event]
+pub struct WinnersSelected {
+    pub challenge_id: u64,
+    pub seed: [u8; 32],
+    pub winners: Vec<Pubkey>,
+    pub seq: u64,
+}
+
+#[event]
+pub struct Totals {
+    pub challenge_id: u64,
+    pub total_collected: u64,
+    pub subscribers_count: u32,
+    pub sponsored_total: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct FeeUpdated {
+    pub challenge_id: u64,
+    pub old_fee: u64,
+    pub new_fee: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct CommissionUpdated {
+    pub challenge_id: u64,
+    pub old_bps: u16,
+    pub new_bps: u16,
+    pub seq: u64,
+}
+
+#[event]
+pub struct PrimaryOwnershipTransferred {
+    pub challenge_id: u64,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub seq: u64,
+}
+
+#[event]
+pub struct ChallengeReset {
+    pub challenge_id: u64,
+    pub subscribe_start: i64,
+    pub subscribe_end: i64,
+    pub timestamp: i64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct RefundRedirected {
+    pub challenge_id: u64,
+    pub subscriber: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct DistributionPreview {
+    pub challenge_id: u64,
+    pub available: u64,
+    pub commission: u64,
+    pub bonus_each: u64,
+    pub winners_count: u32,
+    pub leftover: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct SubscriberInfo {
+    pub challenge_id: u64,
+    pub who: Pubkey,
+    pub subscribed: bool,
+    pub paid: u64,
+    pub joined_at: i64,
+    pub fully_paid: bool,
+    pub seq: u64,
+}
+
+#[event]
+pub struct UnclaimedSwept {
+    pub challenge_id: u64,
+    pub amount: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct AvailableBalance {
+    pub challenge_id: u64,
+    pub total: u64,
+    pub rent_exempt: u64,
+    pub available: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct EmergencyStopped {
+    pub challenge_id: u64,
+    pub by: Pubkey,
+    pub seq: u64,
+}
+
+#[event]
+pub struct TokenConfigured {
+    pub mint: Pubkey,
+    pub decimals: u8,
+    pub seq: u64,
+}
+
+#[event]
+pub struct DistributionStatus {
+    pub challenge_id: u64,
+    pub paid: bool,
+    pub status: u8,
+    pub winners_paid: u32,
+    pub seq: u64,
+}
+
+#[event]
+pub struct ReferralRecorded {
+    pub challenge_id: u64,
+    pub subscriber: Pubkey,
+    pub referrer: Pubkey,
+    pub seq: u64,
+}
+
+#[event]
+pub struct WinnersReplaced {
+    pub challenge_id: u64,
+    pub count: u32,
+    pub seq: u64,
+}
+
+#[event]
+pub struct StatusAutoAdvanced {
+    pub challenge_id: u64,
+    pub from: u8,
+    pub to: u8,
+    pub timestamp: i64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct DistributionReopened {
+    pub challenge_id: u64,
+    pub from_status: u8,
+    pub nonce: u64,
+    pub by: Pubkey,
+    pub seq: u64,
+}
+
+#[event]
+pub struct PaymentCompleted {
+    pub challenge_id: u64,
+    pub subscriber: Pubkey,
+    pub amount: u64,
+    pub total_paid: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct WinnersList {
+    pub challenge_id: u64,
+    pub winners: Vec<Pubkey>,
+    pub amounts: Vec<u64>,
+    pub seq: u64,
+}
+
+#[event]
+pub struct RefundBatchSummary {
+    pub challenge_id: u64,
+    pub count: u32,
+    pub total_refunded: u64,
+    pub balance_before: u64,
+    pub balance_after: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct RolledOver {
+    pub challenge_id: u64,
+    pub from: Pubkey,
+    pub to: Pubkey,
+    pub amount: u64,
+    pub seq: u64,
+}
+
+#[event]
+pub struct OpCheckpoint {
+    pub challenge_id: u64,
+    pub op_counter: u64,
+    pub slot: u64,
+    pub timestamp: i64,
+    pub seq: u64,
 }
@@ -0,0 +1,1518 @@
+// tests.rs
+//
+// Unit tests for the pure helpers plus handler-level tests for the rejection paths. Handlers are
+// called directly: accounts go through the real try_accounts constraints, and the few syscalls
+// the program makes (clock, rent, system transfers) are served by the stubs below.
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::sync::Once;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::entrypoint::{ProgramResult, MAX_PERMITTED_DATA_INCREASE};
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::solana_program::program_utils::limited_deserialize;
+use anchor_lang::solana_program::system_instruction::{SystemError, SystemInstruction};
+use anchor_lang::solana_program::system_program;
+use anchor_lang::{Bumps, Discriminator};
+
+use crate::contexts::*;
+use crate::internal::ErrorCode;
+use crate::internal::*;
+use crate::snzup_subscription;
+
+// Harness
+
+thread_local! {
+    static NOW: Cell<i64> = const { Cell::new(1_700_000_000) };
+    static EVENTS: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_log(&self, _message: &str) {}
+
+    // emit! logs one field: the event discriminator followed by its borsh body
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        EVENTS.with(|e| e.borrow_mut().push(fields[0].to_vec()));
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        let clock = Clock { unix_timestamp: NOW.with(|n| n.get()), ..Clock::default() };
+        unsafe { *(var_addr as *mut Clock) = clock };
+        0
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = Rent::default() };
+        0
+    }
+
+    // System program only: transfers move lamports between the passed accounts, and creating
+    // or allocating an address that already holds an account fails the way the runtime does
+    fn sol_invoke_signed(
+        &self,
+        instruction: &Instruction,
+        account_infos: &[AccountInfo],
+        _signers_seeds: &[&[&[u8]]],
+    ) -> ProgramResult {
+        assert_eq!(instruction.program_id, system_program::ID);
+        let find = |i: usize| {
+            let key = instruction.accounts[i].pubkey;
+            account_infos.iter().find(|a| *a.key == key).unwrap()
+        };
+        let in_use = |a: &AccountInfo| !a.data_is_empty() || *a.owner != system_program::ID;
+        match limited_deserialize(&instruction.data, 1_024).unwrap() {
+            SystemInstruction::Transfer { lamports } => {
+                **find(0).try_borrow_mut_lamports()? -= lamports;
+                **find(1).try_borrow_mut_lamports()? += lamports;
+                Ok(())
+            }
+            SystemInstruction::CreateAccount { .. } if in_use(find(1)) => {
+                Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32))
+            }
+            SystemInstruction::Allocate { .. } if in_use(find(0)) => {
+                Err(ProgramError::Custom(SystemError::AccountAlreadyInUse as u32))
+            }
+            other => panic!("system instruction not stubbed: {:?}", other),
+        }
+    }
+}
+
+fn setup() {
+    static STUBS: Once = Once::new();
+    STUBS.call_once(|| {
+        set_syscall_stubs(Box::new(Stubs));
+    });
+}
+
+fn set_now(ts: i64) {
+    setup();
+    NOW.with(|n| n.set(ts));
+}
+
+fn now() -> i64 {
+    NOW.with(|n| n.get())
+}
+
+// Every event of type T this thread emitted, oldest first
+fn events<T: AnchorDeserialize + Discriminator>() -> Vec<T> {
+    EVENTS.with(|e| {
+        e.borrow()
+            .iter()
+            .filter(|data| data.starts_with(&T::DISCRIMINATOR))
+            .map(|data| T::try_from_slice(&data[8..]).unwrap())
+            .collect()
+    })
+}
+
+fn last_event<T: AnchorDeserialize + Discriminator>() -> Option<T> {
+    events::<T>().pop()
+}
+
+// Accounts live for the whole test run; leaking keeps the AccountInfo lifetimes simple
+fn account(key: Pubkey, owner: Pubkey, lamports: u64, data: Vec<u8>, signer: bool) -> AccountInfo<'static> {
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        signer,
+        true,
+        Box::leak(Box::new(lamports)),
+        Box::leak(data.into_boxed_slice()),
+        Box::leak(Box::new(owner)),
+        false,
+        0,
+    )
+}
+
+fn wallet(key: Pubkey, lamports: u64, signer: bool) -> AccountInfo<'static> {
+    account(key, system_program::ID, lamports, Vec::new(), signer)
+}
+
+const FEE: u64 = 1_000_000;
+
+fn system_program_account() -> AccountInfo<'static> {
+    let mut info = account(system_program::ID, Pubkey::default(), 1, Vec::new(), false);
+    info.executable = true;
+    info
+}
+
+// Placeholder for an omitted optional account: the program id, as clients pass it
+fn none() -> AccountInfo<'static> {
+    account(crate::ID, Pubkey::default(), 0, Vec::new(), false)
+}
+
+// A pending challenge in the current layout, built through from_v1 so every field has its default
+fn new_state() -> State {
+    let owner = Pubkey::new_unique();
+    let mut s = State::from_v1(StateV1 {
+        version: 1,
+        bump: 0,
+        challenge_id: 7,
+        fee: FEE,
+        commission: 10,
+        status: 0,
+        owner,
+        treasury: Pubkey::new_unique(),
+        paid: false,
+        op_counter: 0,
+        owners: Vec::new(),
+        subscribers: Vec::new(),
+        winners_list: Vec::new(),
+    });
+    s.total_collected = 0;
+    s
+}
+
+fn add_subscriber(s: &mut State, who: Pubkey, paid: u64, joined_at: i64) {
+    s.insert_subscriber(who, paid, joined_at, Pubkey::default(), true);
+    s.total_collected += paid;
+}
+
+fn rent_exempt(s: &State) -> u64 {
+    Rent::default().minimum_balance(State::space(s.subscriber_capacity as usize, s.winner_capacity as usize))
+}
+
+fn state_key(s: &State) -> Pubkey {
+    let seeds: &[&[u8]] = &[b"state", s.creator.as_ref(), &s.challenge_id.to_le_bytes()];
+    Pubkey::find_program_address(seeds, &crate::ID).0
+}
+
+fn read_state(info: &AccountInfo) -> State {
+    State::try_deserialize(&mut &info.data.borrow()[..]).unwrap()
+}
+
+// The state PDA holding rent plus everything subscribers and sponsors put in
+fn state_account(mut s: State) -> AccountInfo<'static> {
+    let seeds: &[&[u8]] = &[b"state", s.creator.as_ref(), &s.challenge_id.to_le_bytes()];
+    let (key, bump) = Pubkey::find_program_address(seeds, &crate::ID);
+    s.bump = bump;
+    let lamports = rent_exempt(&s) + s.total_paid().unwrap() + s.sponsored_total;
+    let mut data = vec![0u8; State::space(s.subscriber_capacity as usize, s.winner_capacity as usize)];
+    s.try_serialize(&mut &mut data[..]).unwrap();
+    account(key, crate::ID, lamports, data, false)
+}
+
+// Run the accounts struct's own validation over the given accounts
+fn load<T>(infos: Vec<AccountInfo<'static>>, ix_data: &[u8]) -> Result<(T, T::Bumps)>
+where
+    T: Accounts<'static, T::Bumps> + Bumps,
+    T::Bumps: Default,
+{
+    let mut rest: &'static [AccountInfo<'static>] = Box::leak(infos.into_boxed_slice());
+    let mut bumps = T::Bumps::default();
+    let accounts = T::try_accounts(&crate::ID, &mut rest, ix_data, &mut bumps, &mut BTreeSet::new())?;
+    Ok((accounts, bumps))
+}
+
+fn err(code: ErrorCode) -> Error {
+    code.into()
+}
+
+// Commission math
+
+#[test]
+fn bonus_splits_commission_then_equal_shares() {
+    let b = calculate_competition_bonus(1_000, 10, 100, 3, false).unwrap();
+    assert_eq!(b.commission_amount, 100);
+    assert_eq!(b.bonus_pool, 900);
+    assert_eq!(b.winner_amount, 300);
+}
+
+#[test]
+fn bonus_math_does_not_overflow_on_huge_pools() {
+    let b = calculate_competition_bonus(u64::MAX, 9_999, 10_000, 7, false).unwrap();
+    assert_eq!(b.commission_amount, (u64::MAX as u128 * 9_999 / 10_000) as u64);
+    assert_eq!(b.commission_amount + b.bonus_pool, u64::MAX);
+    assert!(b.winner_amount * 7 <= b.bonus_pool);
+}
+
+#[test]
+fn bonus_math_rejects_a_rate_above_one() {
+    assert_eq!(
+        calculate_competition_bonus(1_000, 2, 1, 1, false).err(),
+        Some(err(ErrorCode::LamportMathError))
+    );
+    assert_eq!(
+        calculate_competition_bonus(1_000, 1, 0, 1, false).err(),
+        Some(err(ErrorCode::LamportMathError))
+    );
+}
+
+#[test]
+fn full_commission_leaves_nothing_for_winners() {
+    let b = calculate_competition_bonus(1_000, 100, 100, 4, false).unwrap();
+    assert_eq!(b.commission_amount, 1_000);
+    assert_eq!(b.bonus_pool, 0);
+    assert_eq!(b.winner_amount, 0);
+}
+
+#[test]
+fn commission_from_bps_uses_wide_math() {
+    assert_eq!(commission_from_bps(u64::MAX, 10_000), u64::MAX);
+    assert_eq!(commission_from_bps(1_000, 250), 25);
+    assert_eq!(commission_from_bps(1_000, 0), 0);
+}
+
+#[test]
+fn discount_comes_off_the_price() {
+    assert_eq!(apply_discount_bps(1_000, 0), 1_000);
+    assert_eq!(apply_discount_bps(1_000, 2_500), 750);
+    assert_eq!(apply_discount_bps(u64::MAX, 10_000), 0);
+}
+
+#[test]
+fn coupon_message_is_bound_to_the_state_account() {
+    let (state_a, state_b, who) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let msg = coupon_message(&state_a, &who, 500, 7);
+    assert_eq!(msg.len(), 32 + 32 + 2 + 8);
+    assert_eq!((&msg[..32], &msg[32..64]), (state_a.as_ref(), who.as_ref()));
+    assert_eq!((&msg[64..66], &msg[66..]), (&500u16.to_le_bytes()[..], &7u64.to_le_bytes()[..]));
+    // Same challenge id under another creator is a different coupon
+    assert_ne!(msg, coupon_message(&state_b, &who, 500, 7));
+}
+
+// Treasury collisions
+
+// Runs set_treasury and hands back the state it left behind
+fn set_treasury(s: State, new_treasury: Pubkey) -> Result<State> {
+    let mut infos = owner_infos(&s);
+    infos.push(wallet(new_treasury, 0, false));
+    run::<SetTreasury, _>(infos, &new_treasury.try_to_vec().unwrap(), |ctx| {
+        snzup_subscription::set_treasury(ctx, new_treasury)
+    })
+    .map(|a| (*a.state).clone())
+}
+
+#[test]
+fn treasury_cannot_be_a_subscriber() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    let subscriber = Pubkey::new_unique();
+    add_subscriber(&mut s, subscriber, FEE, now());
+    assert_eq!(set_treasury(s, subscriber).err(), Some(err(ErrorCode::InvalidInput)));
+}
+
+#[test]
+fn treasury_cannot_be_a_winner() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    let winner = Pubkey::new_unique();
+    s.winners_list.push(winner);
+    assert_eq!(set_treasury(s, winner).err(), Some(err(ErrorCode::InvalidInput)));
+}
+
+#[test]
+fn treasury_can_rotate_to_a_fresh_wallet() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    add_subscriber(&mut s, Pubkey::new_unique(), FEE, now());
+    assert_eq!(validate_treasury(&s, &Pubkey::default()).err(), Some(err(ErrorCode::InvalidInput)));
+    let fresh = Pubkey::new_unique();
+    assert_eq!(set_treasury(s, fresh).unwrap().treasury, fresh);
+}
+
+// Status transitions
+
+// Loads the accounts, runs the handler on them and hands them back for inspection
+fn run<T, F>(infos: Vec<AccountInfo<'static>>, ix_data: &[u8], handler: F) -> Result<T>
+where
+    T: Accounts<'static, T::Bumps> + Bumps,
+    T::Bumps: Default,
+    F: for<'a, 'b> FnOnce(Context<'a, 'b, 'static, 'static, T>) -> Result<()>,
+{
+    let (mut accounts, bumps) = load::<T>(infos, ix_data)?;
+    handler(Context::new(&crate::ID, &mut accounts, &[], bumps))?;
+    Ok(accounts)
+}
+
+// State plus its primary owner as signer: the accounts of every owner-only setter
+fn owner_infos(s: &State) -> Vec<AccountInfo<'static>> {
+    vec![state_account(s.clone()), wallet(s.owner, 1, true)]
+}
+
+// Runs an OnlyOwner handler signed by the primary owner; returns the state it left behind
+fn as_owner<F>(s: State, handler: F) -> Result<State>
+where
+    F: for<'a, 'b> FnOnce(Context<'a, 'b, 'static, 'static, OnlyOwner<'static>>) -> Result<()>,
+{
+    run::<OnlyOwner, _>(owner_infos(&s), &[], handler).map(|a| (*a.state).clone())
+}
+
+#[test]
+fn closed_and_canceled_are_terminal() {
+    assert!(!is_terminal_status(0));
+    assert!(!is_terminal_status(1));
+    assert!(is_terminal_status(2));
+    assert!(is_terminal_status(3));
+}
+
+#[test]
+fn closed_challenge_cannot_go_back_to_pending() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.status = 2;
+    let res = as_owner(s, |ctx| snzup_subscription::set_status(ctx, 0));
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidStatus)));
+}
+
+#[test]
+fn canceled_challenge_cannot_be_restarted() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.mark_canceled(now());
+    let res = as_owner(s, |ctx| snzup_subscription::set_status(ctx, 1));
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidStatus)));
+}
+
+#[test]
+fn pending_and_in_progress_are_reversible() {
+    set_now(1_700_000_000);
+    let s = as_owner(new_state(), |ctx| snzup_subscription::set_status(ctx, 1)).unwrap();
+    assert_eq!(s.status, 1);
+    let s = as_owner(s, |ctx| snzup_subscription::set_status(ctx, 0)).unwrap();
+    assert_eq!(s.status, 0);
+    let s = as_owner(s, |ctx| snzup_subscription::set_status(ctx, 3)).unwrap();
+    assert_eq!((s.status, s.canceled_at), (3, now()));
+}
+
+#[test]
+fn only_the_owner_may_set_status() {
+    set_now(1_700_000_000);
+    let s = new_state();
+    let infos = vec![state_account(s), wallet(Pubkey::new_unique(), 1, true)];
+    assert_eq!(load::<OnlyOwner>(infos, &[]).err(), Some(err(ErrorCode::OnlyOwner)));
+}
+
+// Subscriber membership
+
+#[test]
+fn subscribers_stay_sorted_and_unique() {
+    let mut s = new_state();
+    let keys: Vec<Pubkey> = (0..20).map(|_| Pubkey::new_unique()).rev().collect();
+    for k in &keys {
+        add_subscriber(&mut s, *k, FEE, 0);
+    }
+    s.insert_subscriber(keys[3], 1, 0, Pubkey::default(), true); // already present: no-op
+    assert_eq!(s.subscribers.len(), keys.len());
+    assert!(s.subscribers.windows(2).all(|w| w[0].who < w[1].who));
+    assert!(keys.iter().all(|k| s.is_subscriber(k)));
+    assert_eq!(s.paid_by(&keys[3]), Some(FEE));
+    assert!(!s.is_subscriber(&Pubkey::new_unique()));
+}
+
+// Stand-in for a compute-unit benchmark, which needs a validator: key comparisons are what a
+// membership check spends its CU on, so count them for the sorted search and the old scan
+#[test]
+fn membership_cost_grows_with_log_of_the_subscriber_count() {
+    for n in [10usize, 1000] {
+        let mut s = new_state();
+        for _ in 0..n {
+            add_subscriber(&mut s, Pubkey::new_unique(), FEE, 0);
+        }
+        let missing = Pubkey::new_unique();
+        let mut searched = 0usize;
+        let found = s.subscribers.binary_search_by(|e| {
+            searched += 1;
+            e.who.cmp(&missing)
+        });
+        assert_eq!(found, s.subscriber_index(&missing));
+        let mut scanned = 0usize;
+        assert!(s.subscribers.iter().all(|e| {
+            scanned += 1;
+            e.who != missing
+        }));
+
+        // A miss, the worst case: at most ceil(log2(n)) + 1 comparisons against n for the scan
+        let bound = n.next_power_of_two().trailing_zeros() as usize + 1;
+        assert!(searched <= bound, "{n} subscribers: {searched} comparisons");
+        assert_eq!(scanned, n);
+    }
+}
+
+#[test]
+fn from_v1_sorts_subscribers_for_binary_search() {
+    let keys: Vec<Pubkey> = (0..5).map(|_| Pubkey::new_unique()).rev().collect();
+    let old = StateV1 {
+        version: 1,
+        bump: 254,
+        challenge_id: 3,
+        fee: 500,
+        commission: 10,
+        status: 0,
+        owner: Pubkey::new_unique(),
+        treasury: Pubkey::new_unique(),
+        paid: false,
+        op_counter: 9,
+        owners: Vec::new(),
+        subscribers: keys.clone(),
+        winners_list: Vec::new(),
+    };
+    let s = State::from_v1(old);
+    assert!(s.subscribers.windows(2).all(|w| w[0].who < w[1].who));
+    assert!(keys.iter().all(|k| s.is_subscriber(k)));
+    // v1 only had a flat fee and no join times; totals are backfilled to match
+    assert!(s.subscribers.iter().all(|e| e.paid == 500 && e.joined_at == 0 && e.fully_paid));
+    assert_eq!(s.total_collected, 2_500);
+    assert_eq!((s.version, s.bump, s.op_counter), (State::CURRENT_VERSION, 254, 9));
+    assert_eq!(s.commission_fraction(), (10, 100));
+    assert_eq!((s.subscriber_capacity, s.winner_capacity), (100, 10));
+    assert!(!s.legacy_paid);
+}
+
+// Winner selection
+
+#[test]
+fn strict_mode_rejects_non_subscriber_winners() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.winners_must_be_subscribers = true;
+    let member = Pubkey::new_unique();
+    add_subscriber(&mut s, member, FEE, now());
+    let outsider = Pubkey::new_unique();
+
+    let res = as_owner(s.clone(), |ctx| snzup_subscription::set_winners_list(ctx, vec![outsider]));
+    assert_eq!(res.err(), Some(err(ErrorCode::WinnerNotSubscriber)));
+    let s = as_owner(s, |ctx| snzup_subscription::set_winners_list(ctx, vec![member])).unwrap();
+    assert_eq!(s.winners_list, vec![member]);
+}
+
+#[test]
+fn relaxed_mode_accepts_outside_winners() {
+    set_now(1_700_000_000);
+    let outsider = Pubkey::new_unique();
+    let s = as_owner(new_state(), |ctx| snzup_subscription::set_winners_list(ctx, vec![outsider])).unwrap();
+    assert_eq!(s.winners_list, vec![outsider]);
+}
+
+// Payout accounts
+
+// Runs send_bonus_to_winners signed by the owner at the current nonce. Callers keep clones of
+// the treasury and winner accounts to read balances back afterwards.
+fn send_bonus(
+    state: AccountInfo<'static>,
+    treasury: &AccountInfo<'static>,
+    remaining: &[AccountInfo<'static>],
+) -> Result<State> {
+    let stored = read_state(&state);
+    let infos = vec![state, wallet(stored.owner, 1, true), treasury.clone(), system_program_account(), none()];
+    let (mut accounts, bumps) = load::<SendBonus>(infos, &[])?;
+    let remaining: &'static [AccountInfo<'static>] = Box::leak(remaining.to_vec().into_boxed_slice());
+    let ctx = Context::new(&crate::ID, &mut accounts, remaining, bumps);
+    snzup_subscription::send_bonus_to_winners(ctx, stored.distribution_nonce, None, 0, 0)?;
+    Ok((*accounts.state).clone())
+}
+
+// Three paid-up subscribers, the first two of them winners
+fn closed_with_winners() -> (State, Vec<Pubkey>) {
+    let mut s = new_state();
+    s.status = 1;
+    let keys: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    for k in &keys {
+        add_subscriber(&mut s, *k, FEE, now());
+    }
+    s.winners_list = keys[..2].to_vec();
+    s.canonicalize_winners();
+    let winners = s.winners_list.clone();
+    (s, winners)
+}
+
+#[test]
+fn payout_pays_commission_and_equal_shares() {
+    set_now(1_700_000_000);
+    let (s, winners) = closed_with_winners();
+    let treasury = wallet(s.treasury, 0, false);
+    let winner_infos: Vec<_> = winners.iter().map(|w| wallet(*w, 0, false)).collect();
+    let s = send_bonus(state_account(s), &treasury, &winner_infos).unwrap();
+    assert!(s.paid && s.status == 2 && !s.in_distribution);
+    assert_eq!(treasury.lamports(), 300_000);
+    assert!(winner_infos.iter().all(|w| w.lamports() == 1_350_000));
+    assert_eq!(s.total_distributed, 3 * FEE);
+}
+
+#[test]
+fn sponsored_payout_stays_within_the_refund_budget() {
+    set_now(1_700_000_000);
+    let (mut s, winners) = closed_with_winners();
+    s.sponsored_total = 3 * FEE;
+    let treasury = wallet(s.treasury, 0, false);
+    let winner_infos: Vec<_> = winners.iter().map(|w| wallet(*w, 0, false)).collect();
+    let s = send_bonus(state_account(s), &treasury, &winner_infos).unwrap();
+    // Fees and sponsor money both went out; the invariant still holds
+    assert_eq!(s.total_distributed, 6 * FEE);
+    assert!(s.check_refund_budget(0).is_ok());
+    assert!(s.check_refund_budget(1).is_err());
+}
+
+#[test]
+fn payout_summary_leaves_out_a_leftover_the_pda_keeps() {
+    set_now(1_700_000_000);
+    let (mut s, winners) = closed_with_winners();
+    s.sponsored_total = 1;
+    s.leftover_policy = 2;
+    let treasury = wallet(s.treasury, 0, false);
+    let winner_infos: Vec<_> = winners.iter().map(|w| wallet(*w, 0, false)).collect();
+    let s = send_bonus(state_account(s), &treasury, &winner_infos).unwrap();
+    let summary = last_event::<crate::DistributionSummary>().unwrap();
+    assert_eq!(summary.leftover, 1);
+    assert_eq!(summary.total_distributed, 3 * FEE);
+    assert_eq!(summary.total_distributed, s.total_distributed);
+}
+
+#[test]
+fn payout_rejects_the_state_pda_as_a_winner() {
+    set_now(1_700_000_000);
+    let (mut s, winners) = closed_with_winners();
+    s.winners_list = vec![winners[0], state_key(&s)];
+    s.canonicalize_winners();
+    let state = state_account(s.clone());
+    let remaining: Vec<_> = s
+        .winners_list
+        .iter()
+        .map(|w| if *w == state.key() { state.clone() } else { wallet(*w, 0, false) })
+        .collect();
+    let res = send_bonus(state, &wallet(s.treasury, 0, false), &remaining);
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidWinnerAddress)));
+}
+
+#[test]
+fn payout_rejects_a_winner_account_out_of_order() {
+    set_now(1_700_000_000);
+    let (s, winners) = closed_with_winners();
+    let swapped = vec![wallet(winners[1], 0, false), wallet(winners[0], 0, false)];
+    let res = send_bonus(state_account(s.clone()), &wallet(s.treasury, 0, false), &swapped);
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidWinnerAddress)));
+}
+
+// Operation counter
+
+#[test]
+fn reset_op_counter_zeroes_a_full_counter() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.op_counter = u64::MAX;
+    let s = as_owner(s, snzup_subscription::reset_op_counter).unwrap();
+    assert_eq!(s.op_counter, 0);
+    assert_eq!(s.event_seq, 1);
+}
+
+#[test]
+fn op_counter_reads_do_not_advance_the_sequence() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.op_counter = 41;
+    s.event_seq = 5;
+    let s = as_owner(s, snzup_subscription::get_operation_fee).unwrap();
+    let s = as_owner(s, snzup_subscription::checkpoint).unwrap();
+    assert_eq!((s.op_counter, s.event_seq), (41, 5));
+}
+
+#[test]
+fn silent_setters_still_advance_the_sequence() {
+    set_now(1_700_000_000);
+    let s = new_state();
+    let s = as_owner(s, |ctx| snzup_subscription::set_category(ctx, 1)).unwrap();
+    let s = as_owner(s, |ctx| snzup_subscription::set_status(ctx, 1)).unwrap();
+    let winner = Pubkey::new_unique();
+    let s = as_owner(s, |ctx| snzup_subscription::set_winners_list(ctx, vec![winner])).unwrap();
+    assert_eq!(s.event_seq, 3);
+}
+
+#[test]
+fn cancel_and_refund_tags_every_event_with_one_fresh_seq() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    let member = wallet(Pubkey::new_unique(), 0, false);
+    add_subscriber(&mut s, *member.key, FEE, now());
+    s.event_seq = 4;
+    let infos = vec![
+        state_account(s.clone()),
+        wallet(s.owner, 1, true),
+        system_program_account(),
+        wallet(s.treasury, 0, false),
+    ];
+    let (mut accounts, bumps) = load::<RefundBatch>(infos, &[]).unwrap();
+    let remaining: &'static [AccountInfo<'static>] = Box::leak(vec![member.clone()].into_boxed_slice());
+    snzup_subscription::cancel_and_refund(Context::new(&crate::ID, &mut accounts, remaining, bumps)).unwrap();
+    assert_eq!(accounts.state.event_seq, 5);
+    assert_eq!(member.lamports(), FEE);
+    assert_eq!(last_event::<crate::RefundSent>().unwrap().seq, 5);
+    assert_eq!(last_event::<crate::ChallengeCanceled>().unwrap().seq, 5);
+}
+
+// Commission lock
+
+fn set_commission_bps(s: &State, bps: u16) -> Result<State> {
+    run::<UpdateCommission, _>(owner_infos(s), &[], |ctx| snzup_subscription::set_commission_bps(ctx, bps))
+        .map(|a| (*a.state).clone())
+}
+
+#[test]
+fn commission_is_locked_once_someone_joined() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    assert_eq!(set_commission_bps(&s, 250).unwrap().commission_fraction(), (250, 10_000));
+
+    add_subscriber(&mut s, Pubkey::new_unique(), FEE, now());
+    assert_eq!(set_commission_bps(&s, 250).err(), Some(err(ErrorCode::InvalidStatus)));
+    let res = run::<UpdateCommission, _>(owner_infos(&s), &[], |ctx| {
+        snzup_subscription::set_commission_fraction(ctx, 1, 3)
+    });
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidStatus)));
+    let res = run::<UpdateCommission, _>(owner_infos(&s), &[], |ctx| snzup_subscription::set_commision(ctx, 5));
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidStatus)));
+}
+
+#[test]
+fn commission_above_one_hundred_percent_is_rejected() {
+    set_now(1_700_000_000);
+    let s = new_state();
+    assert_eq!(set_commission_bps(&s, 10_001).err(), Some(err(ErrorCode::InvalidCommissionRate)));
+    let res = run::<UpdateCommission, _>(owner_infos(&s), &[], |ctx| {
+        snzup_subscription::set_commission_fraction(ctx, 4, 3)
+    });
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidCommissionRate)));
+}
+
+// Direct lamport transfers
+
+#[test]
+fn pda_pay_rejects_the_same_account_on_both_sides() {
+    set_now(1_700_000_000);
+    let pda = account(Pubkey::new_unique(), crate::ID, 10_000_000, vec![0; 64], false);
+    assert_eq!(crate::pda_pay(&pda, &pda.clone(), 1).err(), Some(err(ErrorCode::InvalidInput)));
+    assert_eq!(pda.lamports(), 10_000_000);
+}
+
+// Subscribing
+
+// Runs subscribe for `subscriber` with no gate or coupon accounts; returns the accounts
+fn subscribe(s: &State, subscriber: &AccountInfo<'static>, proof: Vec<[u8; 32]>) -> Result<Subscribe<'static>> {
+    let infos = vec![
+        state_account(s.clone()),
+        subscriber.clone(),
+        system_program_account(),
+        none(),
+        none(),
+        none(),
+        none(),
+    ];
+    run::<Subscribe, _>(infos, &[], |ctx| snzup_subscription::subscribe(ctx, proof))
+}
+
+#[test]
+fn subscribe_moves_the_fee_into_the_challenge() {
+    set_now(1_700_000_000);
+    let s = new_state();
+    let who = wallet(Pubkey::new_unique(), 5 * FEE, true);
+    let accounts = subscribe(&s, &who, Vec::new()).unwrap();
+    assert_eq!(who.lamports(), 4 * FEE);
+    assert_eq!(accounts.state.to_account_info().lamports(), rent_exempt(&s) + FEE);
+    assert_eq!(accounts.state.paid_by(who.key), Some(FEE));
+    assert_eq!(accounts.state.subscribers[0].joined_at, now());
+}
+
+#[test]
+fn owners_cannot_subscribe_when_opted_in() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.owners_cannot_subscribe = true;
+    let co_owner = Pubkey::new_unique();
+    s.owners.push(co_owner);
+    for key in [s.owner, co_owner] {
+        let res = subscribe(&s, &wallet(key, 5 * FEE, true), Vec::new());
+        assert_eq!(res.err(), Some(err(ErrorCode::OwnerCannotSubscribe)));
+    }
+    s.owners_cannot_subscribe = false;
+    assert!(subscribe(&s, &wallet(s.owner, 5 * FEE, true), Vec::new()).is_ok());
+}
+
+#[test]
+fn payout_refuses_to_run_inside_another_payout() {
+    set_now(1_700_000_000);
+    let (mut s, winners) = closed_with_winners();
+    s.in_distribution = true;
+    let winner_infos: Vec<_> = winners.iter().map(|w| wallet(*w, 0, false)).collect();
+    let res = send_bonus(state_account(s.clone()), &wallet(s.treasury, 0, false), &winner_infos);
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidStatus)));
+    assert!(winner_infos.iter().all(|w| w.lamports() == 0));
+}
+
+#[test]
+fn chunked_payout_with_nothing_per_winner_emits_no_bonus() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.status = 1;
+    s.sponsored_total = 1; // one lamport over rent: commission and shares all round to zero
+    s.winners_list = vec![Pubkey::new_unique(), Pubkey::new_unique()];
+    s.canonicalize_winners();
+    let winner_infos: Vec<_> = s.winners_list.iter().map(|w| wallet(*w, 0, false)).collect();
+    let infos = vec![
+        state_account(s.clone()),
+        wallet(s.owner, 1, true),
+        wallet(s.treasury, 0, false),
+        system_program_account(),
+        none(),
+    ];
+    let (mut accounts, bumps) = load::<SendBonus>(infos, &[]).unwrap();
+    let remaining: &'static [AccountInfo<'static>] = Box::leak(winner_infos.clone().into_boxed_slice());
+    let before = events::<crate::BonusSent>().len();
+    snzup_subscription::send_bonus_chunk(Context::new(&crate::ID, &mut accounts, remaining, bumps), 0, 0, 2)
+        .unwrap();
+    assert!(accounts.state.paid && accounts.state.bonus_per_winner == 0);
+    assert_eq!(events::<crate::BonusSent>().len(), before);
+}
+
+#[test]
+fn fixed_payout_refuses_to_run_inside_another_payout() {
+    set_now(1_700_000_000);
+    let (mut s, winners) = closed_with_winners();
+    s.winner_amounts = vec![FEE, FEE];
+    let winner_infos: Vec<_> = winners.iter().map(|w| wallet(*w, 0, false)).collect();
+    let run_fixed = |s: &State| {
+        let infos = vec![
+            state_account(s.clone()),
+            wallet(s.owner, 1, true),
+            wallet(s.treasury, 0, false),
+            system_program_account(),
+            none(),
+        ];
+        let (mut accounts, bumps) = load::<SendBonus>(infos, &[])?;
+        let remaining: &'static [AccountInfo<'static>] = Box::leak(winner_infos.clone().into_boxed_slice());
+        let ctx = Context::new(&crate::ID, &mut accounts, remaining, bumps);
+        snzup_subscription::distribute_fixed(ctx, s.distribution_nonce).map(|_| (*accounts.state).clone())
+    };
+
+    s.in_distribution = true;
+    assert_eq!(run_fixed(&s).err(), Some(err(ErrorCode::InvalidStatus)));
+    assert!(winner_infos.iter().all(|w| w.lamports() == 0));
+
+    // The guard is released once the fixed payout goes through
+    s.in_distribution = false;
+    let s = run_fixed(&s).unwrap();
+    assert!(s.paid && !s.in_distribution);
+    assert!(winner_infos.iter().all(|w| w.lamports() == FEE));
+}
+
+#[test]
+fn pda_pay_keeps_the_payer_rent_exempt() {
+    set_now(1_700_000_000);
+    let rent = Rent::default().minimum_balance(64);
+    let pda = account(Pubkey::new_unique(), crate::ID, rent + 500, vec![0; 64], false);
+    let to = wallet(Pubkey::new_unique(), 0, false);
+    assert_eq!(crate::pda_pay(&pda, &to, 501).err(), Some(err(ErrorCode::InsufficientContractBalance)));
+    crate::pda_pay(&pda, &to, 500).unwrap();
+    assert_eq!((pda.lamports(), to.lamports()), (rent, 500));
+}
+
+// Challenge creation
+
+#[test]
+fn initialize_rejects_a_challenge_id_already_in_use() {
+    set_now(1_700_000_000);
+    let s = new_state();
+    let ix_data = (s.challenge_id, FEE, 10u8, Pubkey::new_unique(), 0i64, 100u16, 10u16)
+        .try_to_vec()
+        .unwrap();
+    let infos = vec![state_account(s.clone()), wallet(s.owner, 10 * FEE, true), system_program_account(), none()];
+    let res = load::<Initialize>(infos, &ix_data).map(|_| ());
+    let in_use = ProgramError::Custom(SystemError::AccountAlreadyInUse as u32);
+    assert!(matches!(res, Err(Error::ProgramError(e)) if e.program_error == in_use));
+}
+
+#[test]
+fn frozen_distribution_blocks_payouts_but_not_signups() {
+    set_now(1_700_000_000);
+    let (mut s, winners) = closed_with_winners();
+    s.distribution_frozen = true;
+    let winner_infos: Vec<_> = winners.iter().map(|w| wallet(*w, 0, false)).collect();
+    let res = send_bonus(state_account(s.clone()), &wallet(s.treasury, 0, false), &winner_infos);
+    assert_eq!(res.err(), Some(err(ErrorCode::DistributionFrozen)));
+
+    s.status = 0;
+    assert!(subscribe(&s, &wallet(Pubkey::new_unique(), 5 * FEE, true), Vec::new()).is_ok());
+}
+
+// Allow-list
+
+fn leaf(who: &Pubkey) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hash(who.as_ref()).to_bytes()
+}
+
+fn pair(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+    anchor_lang::solana_program::hash::hashv(&[&lo, &hi]).to_bytes()
+}
+
+#[test]
+fn merkle_proofs_verify_against_sorted_pairs() {
+    let keys: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let leaves: Vec<[u8; 32]> = keys.iter().map(leaf).collect();
+    let (left, right) = (pair(leaves[0], leaves[1]), pair(leaves[2], leaves[3]));
+    let mut s = new_state();
+    s.merkle_root = pair(left, right);
+
+    assert!(verify_allow_list(&s, &keys[0], &[leaves[1], right]).is_ok());
+    assert!(verify_allow_list(&s, &keys[3], &[leaves[2], left]).is_ok());
+    assert_eq!(
+        verify_allow_list(&s, &keys[0], &[leaves[2], right]).err(),
+        Some(err(ErrorCode::NotOnAllowList))
+    );
+    assert_eq!(
+        verify_allow_list(&s, &Pubkey::new_unique(), &[leaves[1], right]).err(),
+        Some(err(ErrorCode::NotOnAllowList))
+    );
+    s.merkle_root = [0u8; 32];
+    assert!(verify_allow_list(&s, &Pubkey::new_unique(), &[]).is_ok());
+}
+
+#[test]
+fn subscribe_requires_a_valid_proof_when_invite_only() {
+    set_now(1_700_000_000);
+    let invited = Pubkey::new_unique();
+    let other = Pubkey::new_unique();
+    let mut s = new_state();
+    s.merkle_root = pair(leaf(&invited), leaf(&other));
+
+    let res = subscribe(&s, &wallet(Pubkey::new_unique(), 5 * FEE, true), vec![leaf(&other)]);
+    assert_eq!(res.err(), Some(err(ErrorCode::NotOnAllowList)));
+    assert!(subscribe(&s, &wallet(invited, 5 * FEE, true), vec![leaf(&other)]).is_ok());
+}
+
+// Emergency stop
+
+fn self_refund(s: &State, subscriber: &AccountInfo<'static>) -> Result<State> {
+    let infos = vec![
+        state_account(s.clone()),
+        subscriber.clone(),
+        wallet(s.treasury, 0, false),
+        system_program_account(),
+    ];
+    run::<SelfRefund, _>(infos, &[], snzup_subscription::self_refund).map(|a| (*a.state).clone())
+}
+
+#[test]
+fn emergency_stop_halts_everything_but_refunds() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    let member = wallet(Pubkey::new_unique(), 0, true);
+    add_subscriber(&mut s, *member.key, FEE, now());
+    let s = as_owner(s, snzup_subscription::emergency_stop).unwrap();
+    assert!(s.emergency_stopped && s.distribution_frozen);
+    assert_eq!((s.status, s.canceled_at), (3, now()));
+
+    let res = subscribe(&s, &wallet(Pubkey::new_unique(), 5 * FEE, true), Vec::new());
+    assert_eq!(res.err(), Some(err(ErrorCode::ChallengeStopped)));
+    for res in [
+        as_owner(s.clone(), |ctx| snzup_subscription::set_status(ctx, 3)),
+        as_owner(s.clone(), |ctx| snzup_subscription::set_winners_list(ctx, vec![*member.key])),
+        as_owner(s.clone(), snzup_subscription::emergency_stop),
+    ] {
+        assert_eq!(res.err(), Some(err(ErrorCode::ChallengeStopped)));
+    }
+
+    // Subscribers still get their money back even though the challenge is CANCELED
+    let s = self_refund(&s, &member).unwrap();
+    assert_eq!(member.lamports(), FEE);
+    assert!(s.subscribers.is_empty());
+}
+
+type OwnerIx = Box<dyn for<'a, 'b> FnOnce(Context<'a, 'b, 'static, 'static, OnlyOwner<'static>>) -> Result<()>>;
+
+fn owner_ix<F>(f: F) -> OwnerIx
+where
+    F: for<'a, 'b> FnOnce(Context<'a, 'b, 'static, 'static, OnlyOwner<'static>>) -> Result<()> + 'static,
+{
+    Box::new(f)
+}
+
+// A challenge with one paid subscriber after emergency_stop
+fn stopped() -> (State, AccountInfo<'static>) {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    let member = wallet(Pubkey::new_unique(), 0, true);
+    add_subscriber(&mut s, *member.key, FEE, now());
+    (as_owner(s, snzup_subscription::emergency_stop).unwrap(), member)
+}
+
+#[test]
+fn every_owner_setter_refuses_a_stopped_challenge() {
+    let (s, member) = stopped();
+    let who = *member.key;
+    let other = Pubkey::new_unique();
+    let table: Vec<(&str, OwnerIx)> = vec![
+        ("set_installment_deadline", owner_ix(|ctx| snzup_subscription::set_installment_deadline(ctx, 0))),
+        ("set_coupon_authority", owner_ix(move |ctx| snzup_subscription::set_coupon_authority(ctx, Some(other)))),
+        ("set_winners_list", owner_ix(move |ctx| snzup_subscription::set_winners_list(ctx, vec![who]))),
+        ("replace_winners", owner_ix(move |ctx| snzup_subscription::replace_winners(ctx, vec![who]))),
+        (
+            "set_winners_with_amounts",
+            owner_ix(move |ctx| snzup_subscription::set_winners_with_amounts(ctx, vec![who], vec![FEE])),
+        ),
+        ("commit_randomness", owner_ix(|ctx| snzup_subscription::commit_randomness(ctx, [1u8; 32]))),
+        ("reveal_and_select", owner_ix(|ctx| snzup_subscription::reveal_and_select(ctx, [1u8; 32], 1))),
+        ("remove_winner", owner_ix(move |ctx| snzup_subscription::remove_winner(ctx, who))),
+        ("remove_owner", owner_ix(move |ctx| snzup_subscription::remove_owner(ctx, other))),
+        ("set_signup_window", owner_ix(|ctx| snzup_subscription::set_signup_window(ctx, 0, 0, 0))),
+        ("reset_for_retry", owner_ix(|ctx| snzup_subscription::reset_for_retry(ctx, 0, 0))),
+        ("set_refund_window", owner_ix(|ctx| snzup_subscription::set_refund_window(ctx, 60))),
+        ("set_refund_fee", owner_ix(|ctx| snzup_subscription::set_refund_fee(ctx, 1))),
+        ("set_category", owner_ix(|ctx| snzup_subscription::set_category(ctx, 1))),
+        ("set_fee_ramp", owner_ix(|ctx| snzup_subscription::set_fee_ramp(ctx, FEE, 2 * FEE, 0, 1))),
+        ("freeze_distribution", owner_ix(|ctx| snzup_subscription::freeze_distribution(ctx, false))),
+        ("emergency_stop", owner_ix(snzup_subscription::emergency_stop)),
+        ("set_gate_collection", owner_ix(|ctx| snzup_subscription::set_gate_collection(ctx, None))),
+        ("set_merkle_root", owner_ix(|ctx| snzup_subscription::set_merkle_root(ctx, [0u8; 32]))),
+        ("set_status", owner_ix(|ctx| snzup_subscription::set_status(ctx, 3))),
+        ("cancel_subscription", owner_ix(move |ctx| snzup_subscription::cancel_subscription(ctx, who))),
+        ("reset_op_counter", owner_ix(snzup_subscription::reset_op_counter)),
+        ("update_metadata_hash", owner_ix(|ctx| snzup_subscription::update_metadata_hash(ctx, [2u8; 32]))),
+        (
+            "set_treasuries",
+            owner_ix(move |ctx| snzup_subscription::set_treasuries(ctx, vec![TreasurySplit { wallet: other, bps: 10_000 }])),
+        ),
+        (
+            "set_approved_commission_recipients",
+            owner_ix(move |ctx| snzup_subscription::set_approved_commission_recipients(ctx, vec![other])),
+        ),
+        ("set_owner", owner_ix(move |ctx| snzup_subscription::set_owner(ctx, other))),
+        ("reopen_distribution", owner_ix(|ctx| snzup_subscription::reopen_distribution(ctx, 0))),
+        ("add_distributor", owner_ix(move |ctx| snzup_subscription::add_distributor(ctx, other))),
+        ("remove_distributor", owner_ix(move |ctx| snzup_subscription::remove_distributor(ctx, other))),
+        ("transfer_primary_ownership", owner_ix(move |ctx| snzup_subscription::transfer_primary_ownership(ctx, other))),
+    ];
+    for (name, ix) in table {
+        assert_eq!(as_owner(s.clone(), ix).err(), Some(err(ErrorCode::ChallengeStopped)), "{name}");
+    }
+}
+
+#[test]
+fn every_other_mutating_instruction_refuses_a_stopped_challenge() {
+    let (s, member) = stopped();
+    let stopped_err = Some(err(ErrorCode::ChallengeStopped));
+    let owner = || wallet(s.owner, 10 * FEE, true);
+    let treasury = || wallet(s.treasury, 0, false);
+    let joiner = || wallet(Pubkey::new_unique(), 5 * FEE, true);
+
+    // Joining, in every flavour
+    assert_eq!(subscribe(&s, &joiner(), Vec::new()).err(), stopped_err, "subscribe");
+    let subscribe_infos = || {
+        vec![state_account(s.clone()), joiner(), system_program_account(), none(), none(), none(), none()]
+    };
+    let res = run::<Subscribe, _>(subscribe_infos(), &[], |ctx| snzup_subscription::subscribe_deposit(ctx, 1, Vec::new()));
+    assert_eq!(res.err(), stopped_err, "subscribe_deposit");
+    let res = run::<Subscribe, _>(subscribe_infos(), &[], |ctx| {
+        snzup_subscription::subscribe_with_referral(ctx, Pubkey::new_unique(), Vec::new())
+    });
+    assert_eq!(res.err(), stopped_err, "subscribe_with_referral");
+    let res = run::<Subscribe, _>(subscribe_infos(), &[], |ctx| {
+        snzup_subscription::subscribe_with_coupon(ctx, 100, [0u8; 64], Vec::new())
+    });
+    assert_eq!(res.err(), stopped_err, "subscribe_with_coupon");
+    let res = run::<Subscribe, _>(subscribe_infos(), &[], |ctx| snzup_subscription::subscribe_exact(ctx, u64::MAX, Vec::new()));
+    assert_eq!(res.err(), stopped_err, "subscribe_exact");
+    let res = run::<SubscribeBatch, _>(vec![state_account(s.clone()), system_program_account()], &[], |ctx| {
+        snzup_subscription::subscribe_batch(ctx, vec![Pubkey::new_unique()], Vec::new())
+    });
+    assert_eq!(res.err(), stopped_err, "subscribe_batch");
+    let res = run::<CompletePayment, _>(
+        vec![state_account(s.clone()), member.clone(), system_program_account()],
+        &[],
+        snzup_subscription::complete_payment,
+    );
+    assert_eq!(res.err(), stopped_err, "complete_payment");
+    let res = run::<SponsorDeposit, _>(vec![state_account(s.clone()), joiner(), system_program_account()], &[], |ctx| {
+        snzup_subscription::sponsor_deposit(ctx, FEE)
+    });
+    assert_eq!(res.err(), stopped_err, "sponsor_deposit");
+
+    // Economics and treasury
+    let res = run::<UpdateFee, _>(owner_infos(&s), &[], |ctx| snzup_subscription::set_fee(ctx, 2 * FEE));
+    assert_eq!(res.err(), stopped_err, "set_fee");
+    let res = run::<UpdateCommission, _>(owner_infos(&s), &[], |ctx| snzup_subscription::set_commision(ctx, 5));
+    assert_eq!(res.err(), stopped_err, "set_commision");
+    assert_eq!(set_commission_bps(&s, 100).err(), stopped_err, "set_commission_bps");
+    let res = run::<UpdateCommission, _>(owner_infos(&s), &[], |ctx| {
+        snzup_subscription::set_commission_fraction(ctx, 1, 3)
+    });
+    assert_eq!(res.err(), stopped_err, "set_commission_fraction");
+    assert_eq!(set_treasury(s.clone(), Pubkey::new_unique()).err(), stopped_err, "set_treasury");
+    let mint = Pubkey::new_unique();
+    let res = run::<ConfigureToken, _>(
+        vec![state_account(s.clone()), owner(), account(mint, Pubkey::default(), 1, Vec::new(), false)],
+        &mint.to_bytes(),
+        |ctx| snzup_subscription::configure_token(ctx, mint, 6),
+    );
+    assert_eq!(res.err(), stopped_err, "configure_token");
+    let res = run::<GrowCapacity, _>(
+        vec![state_account(s.clone()), owner(), system_program_account()],
+        &0u16.to_le_bytes(),
+        |ctx| snzup_subscription::grow_capacity(ctx, 0),
+    );
+    assert_eq!(res.err(), stopped_err, "grow_capacity");
+
+    // Cranks
+    let res = run::<Crank, _>(vec![state_account(s.clone())], &[], snzup_subscription::finalize_signup_window);
+    assert_eq!(res.err(), stopped_err, "finalize_signup_window");
+    let res = run::<Crank, _>(vec![state_account(s.clone())], &[], snzup_subscription::poke);
+    assert_eq!(res.err(), stopped_err, "poke");
+
+    // Money out other than refunds
+    let res = run::<WithdrawExcess, _>(
+        vec![state_account(s.clone()), owner(), treasury()],
+        &[],
+        snzup_subscription::withdraw_excess,
+    );
+    assert_eq!(res.err(), stopped_err, "withdraw_excess");
+    let mut sibling = new_state();
+    sibling.owner = s.owner;
+    sibling.creator = s.creator;
+    sibling.challenge_id = s.challenge_id + 1;
+    let sibling = state_account(sibling);
+    let next = *sibling.key;
+    let res = run::<RolloverTo, _>(vec![state_account(s.clone()), owner(), sibling], &next.to_bytes(), |ctx| {
+        snzup_subscription::rollover_to(ctx, next, 1)
+    });
+    assert_eq!(res.err(), stopped_err, "rollover_to");
+    type PayoutIx = fn(Context<'_, '_, 'static, 'static, SendBonus<'static>>, u64) -> Result<()>;
+    let payouts: [(&str, PayoutIx); 4] = [
+        ("send_bonus_to_winners", |ctx, nonce| snzup_subscription::send_bonus_to_winners(ctx, nonce, None, 0, 0)),
+        ("send_bonus_chunk", |ctx, nonce| snzup_subscription::send_bonus_chunk(ctx, nonce, 0, 1)),
+        ("distribute_fixed", |ctx, nonce| snzup_subscription::distribute_fixed(ctx, nonce)),
+        ("set_winners_and_distribute", |ctx, _| {
+            snzup_subscription::set_winners_and_distribute(ctx, vec![Pubkey::new_unique()])
+        }),
+    ];
+    for (name, ix) in payouts {
+        let infos = vec![state_account(s.clone()), owner(), treasury(), system_program_account(), none()];
+        let res = run::<SendBonus, _>(infos, &[], |ctx| ix(ctx, s.distribution_nonce));
+        assert_eq!(res.err(), stopped_err, "{name}");
+    }
+}
+
+#[test]
+fn stopped_challenge_can_still_refund_and_sweep() {
+    let (s, member) = stopped();
+    let deadline = s.canceled_at + State::MIN_UNCLAIMED_GRACE_SECS;
+    let s = as_owner(s, |ctx| snzup_subscription::set_unclaimed_deadline(ctx, deadline)).unwrap();
+    assert_eq!(s.unclaimed_deadline, deadline);
+
+    // Refunds stay open until the deadline, the sweep takes what is left after it
+    let mut s = s;
+    add_subscriber(&mut s, Pubkey::new_unique(), FEE, now());
+    let s = self_refund(&s, &member).unwrap();
+    assert_eq!(member.lamports(), FEE);
+    set_now(deadline + 1);
+    let treasury = wallet(s.treasury, 0, false);
+    let mut infos = owner_infos(&s);
+    infos.push(treasury.clone());
+    let swept = run::<WithdrawExcess, _>(infos, &[], |ctx| snzup_subscription::sweep_unclaimed(ctx, deadline))
+        .map(|a| (*a.state).clone())
+        .unwrap();
+    assert!(swept.subscribers.is_empty());
+    assert_eq!(treasury.lamports(), FEE);
+}
+
+#[test]
+fn treasury_must_be_system_owned() {
+    set_now(1_700_000_000);
+    let (s, winners) = closed_with_winners();
+    let vault = Pubkey::new_unique();
+    let not_system = anchor_lang::error::ErrorCode::AccountNotSystemOwned;
+
+    let mut infos = owner_infos(&s);
+    infos.push(account(vault, crate::ID, 1_000_000, vec![0; 8], false));
+    let res = load::<SetTreasury>(infos, &vault.try_to_vec().unwrap()).map(|_| ());
+    assert_eq!(res.err(), Some(not_system.into()));
+
+    let program_treasury = account(s.treasury, crate::ID, 1_000_000, vec![0; 8], false);
+    let winner_infos: Vec<_> = winners.iter().map(|w| wallet(*w, 0, false)).collect();
+    let res = send_bonus(state_account(s), &program_treasury, &winner_infos);
+    assert_eq!(res.err(), Some(not_system.into()));
+}
+
+#[test]
+fn payout_latches_before_it_can_run_twice() {
+    set_now(1_700_000_000);
+    let (s, winners) = closed_with_winners();
+    let treasury = wallet(s.treasury, 0, false);
+    let winner_infos: Vec<_> = winners.iter().map(|w| wallet(*w, 0, false)).collect();
+    let state = state_account(s);
+    let s = send_bonus(state.clone(), &treasury, &winner_infos).unwrap();
+    assert_eq!((s.paid, s.distribution_nonce), (true, 1));
+
+    // Replay against the written-back account: the latch holds at the new nonce too
+    let mut data = state.try_borrow_mut_data().unwrap();
+    s.try_serialize(&mut &mut data[..]).unwrap();
+    drop(data);
+    let res = send_bonus(state, &treasury, &winner_infos);
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidStatus)));
+    assert_eq!(treasury.lamports(), 300_000);
+}
+
+#[test]
+fn payout_checks_winner_accounts_before_moving_anything() {
+    set_now(1_700_000_000);
+    let (s, winners) = closed_with_winners();
+    let treasury = wallet(s.treasury, 0, false);
+    let short = vec![wallet(winners[0], 0, false)];
+    let res = send_bonus(state_account(s), &treasury, &short);
+    assert_eq!(res.err(), Some(err(ErrorCode::MissingWinnerAccount)));
+    assert_eq!((treasury.lamports(), short[0].lamports()), (0, 0));
+}
+
+#[test]
+fn payout_failing_mid_loop_leaves_the_stored_latch_unset() {
+    set_now(1_700_000_000);
+    let (s, winners) = closed_with_winners();
+    let state = state_account(s.clone());
+    // The first winner gets paid, then crediting the second overflows its balance
+    let remaining = vec![wallet(winners[0], 0, false), wallet(winners[1], u64::MAX, false)];
+    let res = send_bonus(state.clone(), &wallet(s.treasury, 0, false), &remaining);
+    assert_eq!(res.err(), Some(err(ErrorCode::LamportMathError)));
+    assert_eq!(remaining[0].lamports(), 1_350_000);
+    // Only exit() writes the account back, and a failed instruction never gets there
+    let stored = read_state(&state);
+    assert!(!stored.paid && !stored.in_distribution);
+    assert_eq!((stored.status, stored.distribution_nonce), (1, 0));
+}
+
+// Counter and budget overflow
+
+#[test]
+fn op_counter_saturates_instead_of_failing() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.op_counter = u64::MAX - 1;
+    s.bump_op_counter(5);
+    assert_eq!(s.op_counter, u64::MAX);
+    // A pinned counter never blocks admin work
+    let s = as_owner(s, |ctx| snzup_subscription::set_status(ctx, 1)).unwrap();
+    assert_eq!((s.status, s.op_counter), (1, u64::MAX));
+}
+
+#[test]
+fn refund_budget_counts_refunds_and_payouts() {
+    let mut s = new_state();
+    s.total_collected = 1_000;
+    s.total_refunded = 300;
+    s.total_distributed = 200;
+    assert!(s.check_refund_budget(500).is_ok());
+    assert_eq!(s.check_refund_budget(501).err(), Some(err(ErrorCode::InsufficientContractBalance)));
+    // A payout of sponsor money leaves the subscribers' share of the budget alone
+    s.sponsored_total = 200;
+    s.total_distributed = 400;
+    assert!(s.check_refund_budget(500).is_ok());
+    assert_eq!(s.check_refund_budget(501).err(), Some(err(ErrorCode::InsufficientContractBalance)));
+    s.total_refunded = u64::MAX;
+    assert_eq!(s.check_refund_budget(1).err(), Some(err(ErrorCode::LamportMathError)));
+}
+
+// Rounding direction
+
+#[test]
+fn round_up_gives_the_ceiling_share_from_commission() {
+    let down = calculate_competition_bonus(1_000, 1, 10, 7, false).unwrap();
+    let up = calculate_competition_bonus(1_000, 1, 10, 7, true).unwrap();
+    assert_eq!((down.commission_amount, down.winner_amount), (100, 128));
+    assert_eq!((up.commission_amount, up.winner_amount), (97, 129));
+    assert_eq!(up.commission_amount + up.bonus_pool, 1_000);
+}
+
+#[test]
+fn round_up_never_pays_out_more_than_the_pool() {
+    for pool in [0u64, 1, 2, 99, 1_000, 1_000_003, u64::MAX] {
+        for winners in [1usize, 2, 3, 7, 100] {
+            for (num, den) in [(0u64, 1u64), (1, 3), (9_999, 10_000), (1, 1)] {
+                let b = calculate_competition_bonus(pool, num, den, winners, true).unwrap();
+                let out = b.commission_amount as u128 + b.winner_amount as u128 * winners as u128;
+                assert!(out <= pool as u128, "pool {pool} winners {winners} rate {num}/{den}");
+            }
+        }
+    }
+}
+
+#[test]
+fn owners_cannot_be_winners_unless_opted_in() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    let co_owner = Pubkey::new_unique();
+    s.owners.push(co_owner);
+    for owner in [s.owner, co_owner] {
+        let res = as_owner(s.clone(), |ctx| snzup_subscription::set_winners_list(ctx, vec![owner]));
+        assert_eq!(res.err(), Some(err(ErrorCode::OwnerCannotBeWinner)));
+    }
+    s.allow_owner_winners = true;
+    let s = as_owner(s, |ctx| snzup_subscription::set_winners_list(ctx, vec![co_owner])).unwrap();
+    assert_eq!(s.winners_list, vec![co_owner]);
+}
+
+#[test]
+fn payout_without_winners_needs_full_commission() {
+    set_now(1_700_000_000);
+    let (mut s, _) = closed_with_winners();
+    s.winners_list.clear();
+    let treasury = wallet(s.treasury, 0, false);
+    let res = send_bonus(state_account(s.clone()), &treasury, &[]);
+    assert_eq!(res.err(), Some(err(ErrorCode::NoWinnersConfigured)));
+
+    s.set_commission_bps(State::BPS_DENOMINATOR);
+    let s = send_bonus(state_account(s), &treasury, &[]).unwrap();
+    assert!(s.paid);
+    assert_eq!(treasury.lamports(), 3 * FEE);
+}
+
+// Unclaimed sweep
+
+fn sweep_unclaimed(s: &State, after_ts: i64) -> Result<State> {
+    let mut infos = owner_infos(s);
+    infos.push(wallet(s.treasury, 0, false));
+    run::<WithdrawExcess, _>(infos, &[], |ctx| snzup_subscription::sweep_unclaimed(ctx, after_ts))
+        .map(|a| (*a.state).clone())
+}
+
+#[test]
+fn unclaimed_deadline_must_clear_the_grace_period() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.mark_canceled(now());
+    let early = now() + 60;
+    let res = as_owner(s.clone(), |ctx| snzup_subscription::set_unclaimed_deadline(ctx, early));
+    assert_eq!(res.err(), Some(err(ErrorCode::UnclaimedGraceNotMet)));
+    let deadline = now() + State::MIN_UNCLAIMED_GRACE_SECS;
+    let s = as_owner(s, |ctx| snzup_subscription::set_unclaimed_deadline(ctx, deadline)).unwrap();
+    assert_eq!(s.unclaimed_deadline, deadline);
+}
+
+#[test]
+fn sweep_ignores_a_deadline_set_before_the_cancel() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    add_subscriber(&mut s, Pubkey::new_unique(), FEE, now());
+    s.unclaimed_deadline = now() + 60;
+    s.mark_canceled(now());
+    set_now(now() + 120);
+    let res = sweep_unclaimed(&s, s.unclaimed_deadline);
+    assert_eq!(res.err(), Some(err(ErrorCode::UnclaimedGraceNotMet)));
+
+    s.unclaimed_deadline = s.canceled_at + State::MIN_UNCLAIMED_GRACE_SECS;
+    set_now(s.unclaimed_deadline + 1);
+    let s = sweep_unclaimed(&s, s.unclaimed_deadline).unwrap();
+    assert!(s.subscribers.is_empty());
+}
+
+#[test]
+fn challenges_paid_before_migration_cannot_be_reopened() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.winners_list.push(Pubkey::new_unique());
+    s.paid = true;
+    s.status = 2;
+    s.legacy_paid = true;
+    let nonce = s.distribution_nonce;
+    let res = as_owner(s.clone(), |ctx| snzup_subscription::reopen_distribution(ctx, nonce));
+    assert_eq!(res.err(), Some(err(ErrorCode::DistributionNotReopenable)));
+
+    s.legacy_paid = false;
+    let s = as_owner(s, |ctx| snzup_subscription::reopen_distribution(ctx, nonce)).unwrap();
+    assert_eq!((s.paid, s.status, s.distribution_nonce), (false, 1, nonce + 1));
+}
+
+// Self refunds
+
+#[test]
+fn self_refund_respects_the_refund_window() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    s.refund_window_secs = 3_600;
+    s.refund_fee = 1_000;
+    let member = wallet(Pubkey::new_unique(), 0, true);
+    add_subscriber(&mut s, *member.key, FEE, now());
+
+    set_now(now() + 3_601);
+    assert_eq!(self_refund(&s, &member).err(), Some(err(ErrorCode::RefundWindowClosed)));
+    set_now(now() - 2);
+    let after = self_refund(&s, &member).unwrap();
+    assert_eq!(member.lamports(), FEE - 1_000);
+    assert_eq!(after.total_refunded, FEE);
+}
+
+#[test]
+fn self_refund_is_closed_once_the_challenge_started() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    let member = wallet(Pubkey::new_unique(), 0, true);
+    add_subscriber(&mut s, *member.key, FEE, now());
+    s.status = 1;
+    assert_eq!(self_refund(&s, &member).err(), Some(err(ErrorCode::InvalidStatus)));
+    let stranger = wallet(Pubkey::new_unique(), 0, true);
+    s.status = 0;
+    assert_eq!(self_refund(&s, &stranger).err(), Some(err(ErrorCode::InvalidSubscriberAddress)));
+}
+
+#[test]
+fn money_paths_wait_for_a_chunked_payout_to_finish() {
+    set_now(1_700_000_000);
+    let (mut s, winners) = closed_with_winners();
+    s.status = 0;
+    s.paid_winners.push(winners[0]);
+    let member = wallet(s.subscribers[2].who, 0, true);
+    assert_eq!(self_refund(&s, &member).err(), Some(err(ErrorCode::ChunkedPayoutInProgress)));
+    let res = subscribe(&s, &wallet(Pubkey::new_unique(), 5 * FEE, true), Vec::new());
+    assert_eq!(res.err(), Some(err(ErrorCode::ChunkedPayoutInProgress)));
+    let res = as_owner(s.clone(), |ctx| snzup_subscription::set_status(ctx, 3));
+    assert_eq!(res.err(), Some(err(ErrorCode::ChunkedPayoutInProgress)));
+
+    // After an emergency stop refunds take over
+    s.emergency_stopped = true;
+    assert!(self_refund(&s, &member).is_ok());
+}
+
+// Signup window
+
+#[test]
+fn signup_window_is_checked_against_the_passed_time() {
+    let mut s = new_state();
+    s.subscribe_start = 1_000;
+    s.subscribe_end = 2_000;
+    let who = Pubkey::new_unique();
+    assert_eq!(validate_subscription(&s, &who, 999).err(), Some(err(ErrorCode::SignupWindowNotOpen)));
+    assert!(validate_subscription(&s, &who, 1_000).is_ok());
+    assert!(validate_subscription(&s, &who, 2_000).is_ok());
+    assert_eq!(
+        validate_subscription(&s, &who, 2_001).err(),
+        Some(err(ErrorCode::ChallengeInProgressOrExpired))
+    );
+}
+
+// Account size
+
+#[test]
+fn default_state_size_matches_the_wrapper_env() {
+    // README / wrapper STATE_MAX_SIZE is the size at the default 100 subscribers and 10 winners;
+    // a layout change that moves this must update both, and the wrapper's rent fallback
+    assert_eq!(State::space(100, 10), 9_986);
+    assert_eq!(Rent::default().minimum_balance(9_986), 70_393_440);
+}
+
+#[test]
+fn grow_capacity_refuses_a_step_past_the_realloc_limit() {
+    set_now(1_700_000_000);
+    let s = new_state();
+    // 127 more slots is just over the 10 KiB one realloc may add; caught before any resize
+    let additional = (MAX_PERMITTED_DATA_INCREASE / Subscription::SIZE + 1) as u16;
+    let res = run::<GrowCapacity, _>(
+        vec![state_account(s.clone()), wallet(s.owner, FEE, true), system_program_account()],
+        &additional.to_le_bytes(),
+        |ctx| snzup_subscription::grow_capacity(ctx, additional),
+    );
+    assert_eq!(additional, 127);
+    assert_eq!(res.err(), Some(err(ErrorCode::InvalidInput)));
+}
+
+// Versioning
+
+fn migrate(s: &State) -> Result<State> {
+    let infos = vec![state_account(s.clone()), wallet(s.owner, 1, true), system_program_account()];
+    run::<Migrate, _>(infos, &[], snzup_subscription::migrate).map(|a| read_state(&a.state))
+}
+
+#[test]
+fn version_gated_setters_refuse_a_v2_account() {
+    let mut s = new_state();
+    s.version = 2;
+    assert_eq!(set_commission_bps(&s, 100).err(), Some(err(ErrorCode::NeedsMigration)));
+}
+
+#[test]
+fn migrate_upgrades_a_v2_account_once() {
+    set_now(1_700_000_000);
+    let owner = Pubkey::new_unique();
+    let old = StateV2 {
+        version: 2,
+        bump: 0,
+        challenge_id: 7,
+        fee: FEE,
+        commission: 10,
+        status: 0,
+        owner,
+        treasury: Pubkey::new_unique(),
+        paid: false,
+        op_counter: 3,
+        owners: Vec::new(),
+        subscribers: Vec::new(),
+        winners_list: Vec::new(),
+        distribution_deadline: now() + 60,
+    };
+
+    // v2 PDAs were derived from the owner; the account already has the current default size
+    let seeds: &[&[u8]] = &[b"state", owner.as_ref(), &7u64.to_le_bytes()];
+    let (key, bump) = Pubkey::find_program_address(seeds, &crate::ID);
+    let old = StateV2 { bump, ..old };
+    let len = State::space(StateV1::SUBSCRIBER_CAPACITY as usize, StateV1::WINNER_CAPACITY as usize);
+    let mut data = vec![0u8; len];
+    data[..8].copy_from_slice(&State::DISCRIMINATOR);
+    old.serialize(&mut &mut data[8..]).unwrap();
+    let lamports = Rent::default().minimum_balance(len);
+    let infos = vec![account(key, crate::ID, lamports, data, false), wallet(owner, 1, true), system_program_account()];
+    let migrated = run::<Migrate, _>(infos, &[], snzup_subscription::migrate).map(|a| read_state(&a.state)).unwrap();
+
+    assert_eq!(migrated.version, State::CURRENT_VERSION);
+    assert_eq!((migrated.challenge_id, migrated.owner, migrated.creator), (7, owner, owner));
+    assert_eq!(migrated.distribution_deadline, now() + 60);
+    assert!(set_commission_bps(&migrated, 100).is_ok());
+    assert_eq!(migrate(&migrated).err(), Some(err(ErrorCode::AlreadyMigrated)));
+}
+
+// Collection gate
+
+#[test]
+fn collection_gate_rejects_non_metadata_accounts() {
+    let token_program = anchor_lang::solana_program::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+    let metadata_program = anchor_lang::solana_program::pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+    let mut s = new_state();
+    s.gate_collection = Some(Pubkey::new_unique());
+    let (who, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    let mut token = vec![0u8; 165];
+    token[0..32].copy_from_slice(mint.as_ref());
+    token[32..64].copy_from_slice(who.as_ref());
+    token[64..72].copy_from_slice(&1u64.to_le_bytes());
+    let token = account(Pubkey::new_unique(), token_program, 1, token, false);
+    let (pda, _) = Pubkey::find_program_address(
+        &[b"metadata", metadata_program.as_ref(), mint.as_ref()],
+        &metadata_program,
+    );
+
+    // Right address and owner, but not a MetadataV1 account (or too short to be one)
+    let mut wrong_key = vec![0u8; 200];
+    wrong_key[0] = 6;
+    for data in [wrong_key, vec![4u8]] {
+        let metadata = account(pda, metadata_program, 1, data, false);
+        let res = verify_collection_gate(&s, &who, Some(&token), Some(&metadata));
+        assert_eq!(res.err(), Some(err(ErrorCode::GateNotSatisfied)));
+    }
+}
+
+// Forced refunds
+
+#[test]
+fn forced_refund_keeps_a_closed_challenge_closed() {
+    set_now(1_700_000_000);
+    let mut s = new_state();
+    let member = wallet(Pubkey::new_unique(), 0, true);
+    add_subscriber(&mut s, *member.key, FEE, now());
+    s.status = 2;
+    s.distribution_deadline = now() + 60;
+    set_now(now() + 61);
+
+    let infos = vec![state_account(s.clone()), member.clone(), system_program_account()];
+    let (mut accounts, bumps) = load::<ForceRefundAll>(infos, &[]).unwrap();
+    let remaining: &'static [AccountInfo<'static>] = Box::leak(vec![member.clone()].into_boxed_slice());
+    snzup_subscription::force_refund_range(Context::new(&crate::ID, &mut accounts, remaining, bumps), 0, 1)
+        .unwrap();
+    assert_eq!(member.lamports(), FEE);
+    assert!(accounts.state.subscribers.is_empty());
+    assert_eq!((accounts.state.status, accounts.state.canceled_at), (2, 0));
+}
@@ -51,9 +51,11 @@ export class ApiController {
     }
   }
 
-  // Extract owner header from request
-  private getOwnerHeader(req: Request): string | undefined {
-    const raw = req.headers['x-owner'];
+  // Extract creator header from request; the state PDA is derived from the creator, not the
+  // current owner. x-owner is still read for older callers: until primary ownership could move
+  // it always named the same key.
+  private getCreatorHeader(req: Request): string | undefined {
+    const raw = req.headers['x-creator'] ?? req.headers['x-owner'];
     return Array.isArray(raw) ? raw[0] : (raw as string | undefined);
   }
 
@@ -257,18 +259,18 @@ export class ApiController {
 
   @ApiOperation({ summary: 'Get state for specific challenge' })
   @ApiParam({ name: 'challengeId', description: 'Challenge identifier', example: '123' })
-  @ApiHeader({ name: 'x-owner', required: false, description: 'Owner public key (optional, for multi-owner challenges)' })
+  @ApiHeader({ name: 'x-creator', required: false, description: 'Creator public key the challenge PDA is derived from (optional, defaults to the wrapper wallet)' })
   @ApiResponse({ status: 200, description: 'Challenge state (includes winnersList field)' })
   @Get('state/:challengeId')
   getStateForChallenge(@Param('challengeId') challengeId: string, @Req() req: Request) {
     const id = this.validateChallengeId(challengeId);
-    const owner = this.getOwnerHeader(req);
-    return this.sol.getStateForChallenge(id, owner);
+    const creator = this.getCreatorHeader(req);
+    return this.sol.getStateForChallenge(id, creator);
   }
 
   @ApiOperation({ summary: 'Build subscription transaction for specific challenge' })
   @ApiParam({ name: 'challengeId', description: 'Challenge identifier', example: '123' })
-  @ApiHeader({ name: 'x-owner', required: false, description: 'Owner public key (optional, for multi-owner challenges)' })
+  @ApiHeader({ name: 'x-creator', required: false, description: 'Creator public key the challenge PDA is derived from (optional, defaults to the wrapper wallet)' })
   @ApiBody({ 
     schema: { 
       type: 'object', 
@@ -278,7 +280,13 @@ export class ApiController {
           type: 'string', 
           example: '9WzDXwBbhgHqxw6uBHKVHvQK8196FoePKTLMS8EcnGMW',
           description: 'Subscriber wallet public key' 
-        } 
+        },
+        proof: {
+          type: 'array',
+          items: { type: 'string' },
+          example: [],
+          description: 'Merkle proof (32-byte hex nodes) for invite-only challenges; omit otherwise'
+        }
       } 
     } 
   })
@@ -293,8 +301,10 @@ export class ApiController {
     if (!body?.subscriber || typeof body.subscriber !== 'string') this.bad('subscriber', 'subscriber (string) required');
     
     const subscriber = this.validatePubkey(body.subscriber, 'subscriber');
-    const owner = this.getOwnerHeader(req);
-    return this.sol.buildSubscribeTxForChallenge(id, { subscriber, owner });
+    if (body.proof !== undefined && (!Array.isArray(body.proof) || body.proof.some((n: any) => typeof n !== 'string')))
+      this.bad('proof', 'proof must be an array of hex strings');
+    const creator = this.getCreatorHeader(req);
+    return this.sol.buildSubscribeTxForChallenge(id, { subscriber, creator, proof: body.proof });
   }
 
   @ApiSecurity('admin')
@@ -329,7 +339,7 @@ export class ApiController {
   @ApiOperation({ summary: 'Distribute rewards to winners' })
   @ApiParam({ name: 'challengeId', description: 'Challenge identifier', example: '123' })
   @ApiHeader({ name: 'x-admin-token', required: true, description: 'Admin token' })
-  @ApiHeader({ name: 'x-owner', required: false, description: 'Owner public key (optional, for multi-owner challenges)' })
+  @ApiHeader({ name: 'x-creator', required: false, description: 'Creator public key the challenge PDA is derived from (optional, defaults to the wrapper wallet)' })
   @Post('send-bonus/:challengeId')
   @HttpCode(200)
   sendBonusForChallenge(
@@ -339,8 +349,8 @@ export class ApiController {
     this.checkAdmin(req);
     const id = this.validateChallengeId(challengeId);
     
-    const owner = this.getOwnerHeader(req);
-    return this.sol.sendBonusToWinnersForChallenge(id, owner);
+    const creator = this.getCreatorHeader(req);
+    return this.sol.sendBonusToWinnersForChallenge(id, creator);
   }
 
   @ApiOperation({ summary: 'Check readiness for specific challenge' })
@@ -451,7 +461,7 @@ export class ApiController {
   @ApiOperation({ summary: 'Whitelist owner for specific challenge' })
   @ApiParam({ name: 'challengeId', description: 'Challenge identifier', example: '123' })
   @ApiHeader({ name: 'x-admin-token', required: true, description: 'Admin token' })
-  @ApiHeader({ name: 'x-owner', required: false, description: 'Owner public key (optional)' })
+  @ApiHeader({ name: 'x-creator', required: false, description: 'Creator public key the challenge PDA is derived from (optional, defaults to the wrapper wallet)' })
   @ApiBody({ schema: { type: 'object', required: ['newOwner'], properties: { newOwner: { type: 'string' } } } })
   @Post('set-owner/:challengeId')
   @HttpCode(200)
@@ -464,15 +474,15 @@ export class ApiController {
     const id = this.validateChallengeId(challengeId);
     if (!body?.newOwner || typeof body.newOwner !== 'string') this.bad('newOwner', 'newOwner (string) required');
     const newOwner = this.validatePubkey(body.newOwner, 'newOwner');
-    const owner = this.getOwnerHeader(req);
-    return this.sol.setOwnerForChallenge(id, { newOwner, owner });
+    const creator = this.getCreatorHeader(req);
+    return this.sol.setOwnerForChallenge(id, { newOwner, creator });
   }
 
   @ApiSecurity('admin')
   @ApiOperation({ summary: 'Remove whitelisted owner for specific challenge' })
   @ApiParam({ name: 'challengeId', description: 'Challenge identifier', example: '123' })
   @ApiHeader({ name: 'x-admin-token', required: true, description: 'Admin token' })
-  @ApiHeader({ name: 'x-owner', required: false, description: 'Owner public key (optional)' })
+  @ApiHeader({ name: 'x-creator', required: false, description: 'Creator public key the challenge PDA is derived from (optional, defaults to the wrapper wallet)' })
   @ApiBody({ schema: { type: 'object', required: ['user'], properties: { user: { type: 'string' } } } })
   @Post('remove-owner/:challengeId')
   @HttpCode(200)
@@ -485,15 +495,15 @@ export class ApiController {
     const id = this.validateChallengeId(challengeId);
     if (!body?.user || typeof body.user !== 'string') this.bad('user', 'user (string) required');
     const user = this.validatePubkey(body.user, 'user');
-    const owner = this.getOwnerHeader(req);
-    return this.sol.removeOwnerForChallenge(id, { user, owner });
+    const creator = this.getCreatorHeader(req);
+    return this.sol.removeOwnerForChallenge(id, { user, creator });
   }
 
   @ApiSecurity('admin')
   @ApiOperation({ summary: 'Cancel a subscriber for specific challenge (admin only)' })
   @ApiParam({ name: 'challengeId', description: 'Challenge identifier', example: '123' })
   @ApiHeader({ name: 'x-admin-token', required: true, description: 'Admin token' })
-  @ApiHeader({ name: 'x-owner', required: false, description: 'Owner public key (optional)' })
+  @ApiHeader({ name: 'x-creator', required: false, description: 'Creator public key the challenge PDA is derived from (optional, defaults to the wrapper wallet)' })
   @ApiBody({ 
     schema: { 
       type: 'object', 
@@ -518,15 +528,15 @@ export class ApiController {
     const id = this.validateChallengeId(challengeId);
     if (!body?.subscriber || typeof body.subscriber !== 'string') this.bad('subscriber', 'subscriber (string) required');
     const subscriber = this.validatePubkey(body.subscriber, 'subscriber');
-    const owner = this.getOwnerHeader(req);
-    return this.sol.cancelSubscriptionForChallenge(id, { subscriber, owner });
+    const creator = this.getCreatorHeader(req);
+    return this.sol.cancelSubscriptionForChallenge(id, { subscriber, creator });
   }
 
   @ApiSecurity('admin')
   @ApiOperation({ summary: 'Update treasury address for specific challenge' })
   @ApiParam({ name: 'challengeId', description: 'Challenge identifier', example: '123' })
   @ApiHeader({ name: 'x-admin-token', required: true, description: 'Admin token' })
-  @ApiHeader({ name: 'x-owner', required: false, description: 'Owner public key (optional)' })
+  @ApiHeader({ name: 'x-creator', required: false, description: 'Creator public key the challenge PDA is derived from (optional, defaults to the wrapper wallet)' })
   @ApiBody({ schema: { type: 'object', required: ['newTreasury'], properties: { newTreasury: { type: 'string' } } } })
   @Post('set-treasury/:challengeId')
   @HttpCode(200)
@@ -539,7 +549,7 @@ export class ApiController {
     const id = this.validateChallengeId(challengeId);
     if (!body?.newTreasury || typeof body.newTreasury !== 'string') this.bad('newTreasury', 'newTreasury (string) required');
     const newTreasury = this.validatePubkey(body.newTreasury, 'newTreasury');
-    const owner = this.getOwnerHeader(req);
-    return this.sol.setTreasuryForChallenge(id, { newTreasury, owner });
+    const creator = this.getCreatorHeader(req);
+    return this.sol.setTreasuryForChallenge(id, { newTreasury, creator });
   }
 }
@@ -1,5 +1,5 @@
 import { ApiProperty } from '@nestjs/swagger';
-import { IsArray, IsNumber, IsString, ArrayNotEmpty } from 'class-validator';
+import { IsArray, IsNumber, IsString, ArrayNotEmpty, IsOptional } from 'class-validator';
 
 export class InitializeDto {
   @ApiProperty({ ex: '1', description: 'Unique challenge identifier' })
@@ -19,6 +19,12 @@ export class BuildSubscribeDto {
   @ApiProperty({ ex: 'EXAMPLE_USER_PUBKEY', description: 'User wallet public key (base58)' })
   @IsString()
   subscriber: string;
+
+  @ApiProperty({ type: [String], required: false, description: 'Merkle proof (32-byte hex nodes) for invite-only challenges' })
+  @IsOptional()
+  @IsArray()
+  @IsString({ each: true })
+  proof?: string[];
 }
 
 export class WinnersDto {
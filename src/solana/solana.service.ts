@@ -21,12 +21,19 @@ const STATE_DISC = crypto
   .digest()
   .subarray(0, 8);
 
-// Pre-made ID codes for common operations
-const SUBSCRIBE_DISC = crypto
-  .createHash('sha256')
-  .update('global:subscribe')
-  .digest()
-  .subarray(0, 8);
+// Event discriminators: "Program data: " log lines start with sha256("event:<Name>")[..8]
+const EVENT_NAMES: string[] = (() => {
+  for (const path of [`${__dirname}/../idl/snzup_subscription.json`, `${process.cwd()}/src/idl/snzup_subscription.json`]) {
+    try {
+      const idl = JSON.parse(fs.readFileSync(path, 'utf8'));
+      return (idl.events || []).map((e: any) => e.name);
+    } catch { /* try the next location */ }
+  }
+  return [];
+})();
+const EVENT_BY_DISC = new Map<string, string>(
+  EVENT_NAMES.map(n => [crypto.createHash('sha256').update(`event:${n}`).digest().subarray(0, 8).toString('hex'), n]),
+);
 
 // Helper functions 
 const MAX_U64 = new BN('18446744073709551615');
@@ -69,6 +76,17 @@ function readVecPkSafe(buf: Buffer, o: number) {
   return { v: out, o };
 }
 
+// Vec<Subscription>: who(32) paid(8) joined_at(8) referrer(32) fully_paid(1); only `who` is surfaced
+const SUBSCRIPTION_SIZE = 81;
+function readVecSubscriptionSafe(buf: Buffer, o: number) {
+  const len = buf.readUInt32LE(o); o += 4;
+  const max = Math.floor((buf.length - o) / SUBSCRIPTION_SIZE);
+  if (len < 0 || len > max) throw new Error(`Bad Vec<Subscription> length: ${len} (max ${max})`);
+  const out = new Array<PublicKey>(len);
+  for (let i = 0; i < len; i++) { out[i] = new PublicKey(buf.subarray(o, o + 32)); o += SUBSCRIPTION_SIZE; }
+  return { v: out, o };
+}
+
 type StateHeader = {
   version: number;
   bump: number;
@@ -84,7 +102,7 @@ type StateHeader = {
 };
 
 // Matches on-chain constants -from evm 
-const STATE_MAX_SIZE = Number(process.env.STATE_MAX_SIZE ?? 3793);
+const STATE_MAX_SIZE = Number(process.env.STATE_MAX_SIZE ?? 9986);
 
 // State decode
 function readU64BN(buf: Buffer, o: number) {
@@ -220,25 +238,25 @@ export class SolanaService {
     try { (this.connection as any)?._rpcWebSocket?.close?.(); } catch {}
   }
 
-  // PDA: ["state", owner, challenge_id_le]
-  private deriveStatePda(owner: PublicKey, challengeId: bigint | number): PublicKey {
+  // PDA: ["state", creator, challenge_id_le]
+  private deriveStatePda(creator: PublicKey, challengeId: bigint | number): PublicKey {
     const buf = Buffer.alloc(8); buf.writeBigUInt64LE(BigInt(challengeId));
-    const [pda] = PublicKey.findProgramAddressSync([Buffer.from('state'), owner.toBuffer(), buf], this.programId);
+    const [pda] = PublicKey.findProgramAddressSync([Buffer.from('state'), creator.toBuffer(), buf], this.programId);
     return pda;
   }
 
-  // Helper to pick owner from string or default to wallet
-  private pickOwner(ownerStr?: string): PublicKey {
-    if (!ownerStr) {
-      this.logger.warn('No x-owner header provided, using wrapper wallet as owner. This may indicate integration issues.');
+  // Helper to pick the challenge creator from string or default to wallet (initialize signs with it)
+  private pickCreator(creatorStr?: string): PublicKey {
+    if (!creatorStr) {
+      this.logger.warn('No x-creator header provided, using wrapper wallet as creator. This may indicate integration issues.');
     }
-    return ownerStr ? new PublicKey(ownerStr) : this.wallet.publicKey;
+    return creatorStr ? new PublicKey(creatorStr) : this.wallet.publicKey;
   }
 
-  // Derive PDA with explicit owner and challenge ID
-  private statePdaFor(owner: PublicKey, challengeId: string): PublicKey {
+  // Derive PDA from the creator and challenge ID; the seed stays put when primary ownership moves
+  private statePdaFor(creator: PublicKey, challengeId: string): PublicKey {
     if (!challengeId) this.fail(400, 'BAD_INPUT', 'challengeId required');
-    return this.deriveStatePda(owner, BigInt(challengeId));
+    return this.deriveStatePda(creator, BigInt(challengeId));
   }
 
 
@@ -281,10 +299,10 @@ export class SolanaService {
       if (process.env.ALLOW_RENT_FALLBACK !== '1') {
         this.fail(503, 'RENT_QUERY_FAILED', 'getMinimumBalanceForRentExemption failed');
       }
-      // Pre-calculated rent exemption for STATE_MAX_SIZE=3793 bytes (fallback mode)
+      // Pre-calculated rent exemption for STATE_MAX_SIZE=9986 bytes (fallback mode)
       // NOTE: This value may become stale if Solana changes rent calculations
       this.logger.error('FALLBACK MODE: Using hardcoded rent value due to RPC failure. Verify this is expected!');
-      this.rentFloorLamportsCache = 27290160;
+      this.rentFloorLamportsCache = 70393440;
       return this.rentFloorLamportsCache;
     }
   }
@@ -304,8 +322,8 @@ export class SolanaService {
   }
 
 
-  private invalidateStateCacheForChallenge(challengeId: string, owner?: PublicKey) {
-    const o = owner ?? this.wallet.publicKey;
+  private invalidateStateCacheForChallenge(challengeId: string, creator?: PublicKey) {
+    const o = creator ?? this.wallet.publicKey;
     const key = 'state:' + this.statePdaFor(o, challengeId).toBase58();
     this.cache.del(key);
   }
@@ -414,22 +432,25 @@ export class SolanaService {
   }
 
 
-  // Read vectors sequentially (owners → subscribers → winners)
-  private safeDecodeVectors(data: Buffer, vecOffset: number): { owners: PublicKey[]; subscribers: PublicKey[]; winnersList: PublicKey[] } {
+  // Read vectors sequentially (owners → subscribers → winners), then the scalars right after them
+  // (distribution_deadline i64, subscriber_capacity u16, distribution_nonce u64)
+  private safeDecodeVectors(data: Buffer, vecOffset: number): { owners: PublicKey[]; subscribers: PublicKey[]; winnersList: PublicKey[]; distributionNonce: BN } {
     try {
       let o = vecOffset;
       const r1 = readVecPkSafe(data, o); const owners = r1.v; o = r1.o;
-      const r2 = readVecPkSafe(data, o); const subscribers = r2.v; o = r2.o;
-      const r3 = readVecPkSafe(data, o); const winnersList = r3.v;
-      
+      const r2 = readVecSubscriptionSafe(data, o); const subscribers = r2.v; o = r2.o;
+      const r3 = readVecPkSafe(data, o); const winnersList = r3.v; o = r3.o;
+      o += 8 + 2; // distribution_deadline, subscriber_capacity
+      const distributionNonce = readU64BN(data, o).v;
+
       // Decoder sanity check: warn if vectors are empty but state suggests otherwise
-      return { owners, subscribers, winnersList };
+      return { owners, subscribers, winnersList, distributionNonce };
     } catch (e) {
       //
       // Soft-fail mode for vector decode errors (for development/testing only)
       if (process.env.ALLOW_VECTOR_DECODE_SOFTFAIL === '1') {
         this.logger.error(`SOFT-FAIL MODE: Vector decode failed: ${e?.message || e}. Using empty arrays. THIS SHOULD NOT HAPPEN IN PRODUCTION!`);
-        return { owners: [], subscribers: [], winnersList: [] };
+        return { owners: [], subscribers: [], winnersList: [], distributionNonce: new BN(0) };
       } else {
         this.fail(422, 'DECODE_VECTORS_FAILED', `Vector decode failed: ${String(e)}`);
       }
@@ -440,14 +461,33 @@ export class SolanaService {
   // IX data builder
   private buildIxData(ixName: string, args: any): Buffer {
     switch (ixName) {
-      case 'initialize': { // (challenge_id: u64, fee: u64, commission: u8, treasury: Pubkey)
+      case 'initialize': {
+        // (challenge_id: u64, fee: u64, commission: u8, treasury: Pubkey, distribution_deadline: i64,
+        //  subscriber_capacity: u16, winner_capacity: u16, metadata_hash: [u8; 32],
+        //  winners_must_be_subscribers: bool, min_fee: u64, max_fee: u64, category: u8,
+        //  owners_cannot_subscribe: bool, allow_program_winners: bool, max_tvl: u64,
+        //  leftover_policy: u8, winners_lock_status: u8, max_winner_ratio_bps: u16,
+        //  round_winners_up: bool, allow_owner_winners: bool)
+        // Options the wrapper does not expose yet are sent as 0/false (off); capacities match STATE_MAX_SIZE.
         const d = ixDisc('initialize');
-        const b = Buffer.alloc(8 + 8 + 8 + 1 + 32);
+        const b = Buffer.alloc(8 + 127);
         d.copy(b, 0);
-        b.writeBigUInt64LE(BigInt(toU64BN(args.challengeId).toString()), 8);
-        b.writeBigUInt64LE(BigInt(toU64BN(args.fee).toString()), 16);
-        b.writeUInt8(toU8Number(args.commission), 24);
-        toPubkey(args.treasury).toBuffer().copy(b, 25);
+        let o = 8;
+        b.writeBigUInt64LE(BigInt(toU64BN(args.challengeId).toString()), o); o += 8;
+        b.writeBigUInt64LE(BigInt(toU64BN(args.fee).toString()), o); o += 8;
+        b.writeUInt8(toU8Number(args.commission), o); o += 1;
+        toPubkey(args.treasury).toBuffer().copy(b, o); o += 32;
+        b.writeBigInt64LE(BigInt(args.distributionDeadline ?? 0), o); o += 8;
+        b.writeUInt16LE(args.subscriberCapacity ?? 100, o); o += 2;
+        b.writeUInt16LE(args.winnerCapacity ?? 10, o); o += 2;
+        o += 32;                                           // metadata_hash (zeros = none)
+        o += 1;                                            // winners_must_be_subscribers
+        o += 8 + 8;                                        // min_fee, max_fee (0 = no bound)
+        o += 1 + 1 + 1;                                    // category, owners_cannot_subscribe, allow_program_winners
+        o += 8;                                            // max_tvl (0 = unlimited)
+        o += 1 + 1;                                        // leftover_policy (treasury), winners_lock_status
+        o += 2 + 1 + 1;                                    // max_winner_ratio_bps, round_winners_up, allow_owner_winners
+        if (o !== b.length) throw new Error(`initialize encoding size mismatch: ${o} != ${b.length}`);
         return b;
       }
       case 'set_fee': { // (fee: u64)
@@ -510,16 +550,27 @@ export class SolanaService {
         subs.forEach((pk, i) => pk.toBuffer().copy(b, 12 + i * 32));
         return b;
       }
-      case 'send_bonus_to_winners': { // no args
+      case 'send_bonus_to_winners': {
+        // (expected_nonce: u64, commission_recipient: Option<Pubkey>, expected_available: u64, tolerance: u64)
+        // No commission override and no balance pin (both zero = no check)
         const d = ixDisc('send_bonus_to_winners');
-        const b = Buffer.alloc(8);
+        const b = Buffer.alloc(8 + 8 + 1 + 8 + 8);
         d.copy(b, 0);
+        b.writeBigUInt64LE(BigInt(toU64BN(args.expectedNonce ?? 0).toString()), 8);
+        b.writeUInt8(0, 16); // None
         return b;
       }
-      case 'subscribe': { // no args
+      case 'subscribe': { // (proof: Vec<[u8; 32]>) — empty unless the challenge has an allow-list
+        const proof: Buffer[] = (args.proof || []).map((h: string) => {
+          const node = Buffer.from(String(h).replace(/^0x/, ''), 'hex');
+          if (node.length !== 32) throw new Error(`proof node must be 32 bytes: ${h}`);
+          return node;
+        });
         const d = ixDisc('subscribe');
-        const b = Buffer.alloc(8);
+        const b = Buffer.alloc(8 + 4 + 32 * proof.length);
         d.copy(b, 0);
+        b.writeUInt32LE(proof.length, 8);
+        proof.forEach((node, i) => node.copy(b, 12 + i * 32));
         return b;
       }
       case 'set_treasury': { // (new_treasury: Pubkey)
@@ -625,6 +676,7 @@ export class SolanaService {
       { pubkey: pda, isSigner: false, isWritable: true },                  // state
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: true }, // owner/payer
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: this.programId, isSigner: false, isWritable: false },      // owner_index: None
     ];
     const signature = await this.sendIx('initialize', keys, { challengeId, fee, commission: commision, treasury });
     this.invalidateStateCacheForChallenge(challengeId.toString());
@@ -685,7 +737,17 @@ export class SolanaService {
     try {
       const tx = await this.rpc('getTransaction', () => this.connection.getTransaction(signature, { commitment: 'confirmed', maxSupportedTransactionVersion: 0 }));
       if (!tx) return { error: 'Transaction not found' };
-      return { events: tx.meta?.logMessages || [] };
+      const logs = tx.meta?.logMessages || [];
+      // Every program event ends with `seq: u64`, so the last 8 bytes order events across txs
+      const decoded = logs
+        .filter(l => l.startsWith('Program data: '))
+        .map(l => Buffer.from(l.slice('Program data: '.length), 'base64'))
+        .filter(b => b.length >= 16 && EVENT_BY_DISC.has(b.subarray(0, 8).toString('hex')))
+        .map(b => ({
+          name: EVENT_BY_DISC.get(b.subarray(0, 8).toString('hex')),
+          seq: new BN(b.subarray(b.length - 8), 'le').toString(),
+        }));
+      return { events: logs, decoded };
     } catch (e: any) {
       return { source: 'wrapper', category: 'rpc', error: String(e?.message ?? e) };
     }
@@ -786,10 +848,10 @@ export class SolanaService {
     return this.initialize({ challengeId, fee: params.fee, commision: params.commision });
   }
 
-  // Get state for specific challenge with optional owner override
-  async getStateForChallenge(challengeId: string, ownerStr?: string) {
-    const owner = this.pickOwner(ownerStr);
-    const pda = this.statePdaFor(owner, challengeId);
+  // Get state for specific challenge with optional creator override
+  async getStateForChallenge(challengeId: string, creatorStr?: string) {
+    const creator = this.pickCreator(creatorStr);
+    const pda = this.statePdaFor(creator, challengeId);
     const key = 'state:' + pda.toBase58();
     const cached = this.cache.get<any>(key);
     if (cached) return cached;
@@ -826,8 +888,8 @@ export class SolanaService {
     }
   }
 
-  // Build subscribe transaction for specific challenge with optional owner
-  async buildSubscribeTxForChallenge(challengeId: string, params: { subscriber: string, owner?: string }) {
+  // Build subscribe transaction for specific challenge with optional creator
+  async buildSubscribeTxForChallenge(challengeId: string, params: { subscriber: string, creator?: string, proof?: string[] }) {
     return this.buildGate.run(async () => {
       // Parse subscriber (only validation as we do it with the wrapper)
       let subscriber: PublicKey;
@@ -842,9 +904,9 @@ export class SolanaService {
         }, 400);
       }
 
-      // Derive PDA with specific challenge ID and owner(Getting from the main backend)
-      const owner = this.pickOwner(params.owner);
-      const pda = this.statePdaFor(owner, challengeId);
+      // Derive PDA with specific challenge ID and creator(Getting from the main backend)
+      const creator = this.pickCreator(params.creator);
+      const pda = this.statePdaFor(creator, challengeId);
 
       // Verify state exists and is open before building tx
       const data = await this.getAccountData(pda); // throws if missing/owner mismatch
@@ -856,15 +918,23 @@ export class SolanaService {
       // One RPC
       const { blockhash, lastValidBlockHeight } = await this.bh.get(this.connection);
 
-      // Precomputed discriminator 
+      let data: Buffer;
+      try { data = this.buildIxData('subscribe', { proof: params.proof }); }
+      catch (e: any) { this.fail(400, 'BAD_INPUT', String(e?.message ?? e)); }
+
+      // Optional gate/coupon accounts are passed as the program id (= None)
       const ix = new TransactionInstruction({
         programId: this.programId,
         keys: [
           { pubkey: pda, isSigner: false, isWritable: true },
           { pubkey: subscriber, isSigner: true, isWritable: true },
           { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+          { pubkey: this.programId, isSigner: false, isWritable: false }, // gate_token_account
+          { pubkey: this.programId, isSigner: false, isWritable: false }, // gate_metadata
+          { pubkey: this.programId, isSigner: false, isWritable: false }, // instructions_sysvar
+          { pubkey: this.programId, isSigner: false, isWritable: false }, // coupon_record
         ],
-        data: SUBSCRIBE_DISC,
+        data,
       });
 
       const ixs = [];
@@ -892,10 +962,10 @@ export class SolanaService {
     });
   }
 
-  // Set status for specific challenge with optional owner
-  async setStatusForChallenge(challengeId: string, params: { status: number, owner?: string }) {
-    const owner = this.pickOwner(params.owner);
-    const pda = this.statePdaFor(owner, challengeId);
+  // Set status for specific challenge with optional creator
+  async setStatusForChallenge(challengeId: string, params: { status: number, creator?: string }) {
+    const creator = this.pickCreator(params.creator);
+    const pda = this.statePdaFor(creator, challengeId);
     const keys: AccountMeta[] = [
       { pubkey: pda, isSigner: false, isWritable: true },
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
@@ -905,10 +975,10 @@ export class SolanaService {
     return { signature, challengeId };
   }
 
-  // Set fee for specific challenge with optional owner
-  async setFeeForChallenge(challengeId: string, params: { fee: string, owner?: string }) {
-    const owner = this.pickOwner(params.owner);
-    const pda = this.statePdaFor(owner, challengeId);
+  // Set fee for specific challenge with optional creator
+  async setFeeForChallenge(challengeId: string, params: { fee: string, creator?: string }) {
+    const creator = this.pickCreator(params.creator);
+    const pda = this.statePdaFor(creator, challengeId);
     const keys: AccountMeta[] = [
       { pubkey: pda, isSigner: false, isWritable: true },
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
@@ -918,10 +988,10 @@ export class SolanaService {
     return { signature, challengeId };
   }
 
-  // Set commission for specific challenge with optional owner (note: internal uses misspelled 'set_commision')
-  async setCommissionForChallenge(challengeId: string, params: { commission: number, owner?: string }) {
-    const owner = this.pickOwner(params.owner);
-    const pda = this.statePdaFor(owner, challengeId);
+  // Set commission for specific challenge with optional creator (note: internal uses misspelled 'set_commision')
+  async setCommissionForChallenge(challengeId: string, params: { commission: number, creator?: string }) {
+    const creator = this.pickCreator(params.creator);
+    const pda = this.statePdaFor(creator, challengeId);
     const keys: AccountMeta[] = [
       { pubkey: pda, isSigner: false, isWritable: true },
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
@@ -932,10 +1002,10 @@ export class SolanaService {
     return { signature, challengeId };
   }
 
-  // Set winners for specific challenge with optional owner
-  async setWinnersListForChallenge(challengeId: string, params: { winners: string[], owner?: string }) {
-    const owner = this.pickOwner(params.owner);
-    const pda = this.statePdaFor(owner, challengeId);
+  // Set winners for specific challenge with optional creator
+  async setWinnersListForChallenge(challengeId: string, params: { winners: string[], creator?: string }) {
+    const creator = this.pickCreator(params.creator);
+    const pda = this.statePdaFor(creator, challengeId);
     const winners = params.winners.map(w => new PublicKey(w));
     
     // Winners go in instruction data, not as accounts
@@ -949,11 +1019,11 @@ export class SolanaService {
     return { signature, challengeId };
   }
 
-  // Send bonus to winners for specific challenge with owner
-  async sendBonusToWinnersForChallenge(challengeId: string, ownerStr?: string) {
+  // Send bonus to winners for specific challenge with creator
+  async sendBonusToWinnersForChallenge(challengeId: string, creatorStr?: string) {
     return this.distributionGate.run(async () => {
-      const owner = this.pickOwner(ownerStr);
-      const pda = this.statePdaFor(owner, challengeId);
+      const creator = this.pickCreator(creatorStr);
+      const pda = this.statePdaFor(creator, challengeId);
 
       // Load state & vectors
       const full = await this.getAccountData(pda);
@@ -977,9 +1047,9 @@ export class SolanaService {
       }
 
       // Guards (same as global)
-      // 64-account cap: state, owner, treasury, system + winners
+      // 64-account cap: state, owner, treasury, system, owner_wallet + winners
       const MAX_TX_ACCOUNTS = 64;
-      const BASE_KEYS = 4;
+      const BASE_KEYS = 5;
       if (vecs.winnersList.length > (MAX_TX_ACCOUNTS - BASE_KEYS)) {
         this.fail(422, 'TOO_MANY_WINNERS_FOR_SINGLE_TX',
           `Winners=${vecs.winnersList.length} exceed per-tx account limit (~${MAX_TX_ACCOUNTS - BASE_KEYS}).`);
@@ -1005,11 +1075,12 @@ export class SolanaService {
         { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
         { pubkey: hdr.treasury, isSigner: false, isWritable: true },
         { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+        { pubkey: hdr.owner, isSigner: false, isWritable: true },           // owner_wallet (leftover_policy = owner)
         ...vecs.winnersList.map(w => ({ pubkey: w, isSigner: false, isWritable: true } as AccountMeta)),
       ];
 
       try {
-        const signature = await this.sendIx('send_bonus_to_winners', keys, {});
+        const signature = await this.sendIx('send_bonus_to_winners', keys, { expectedNonce: vecs.distributionNonce });
         this.cache.del('state:' + pda.toBase58());
         const result = { signature, winners: vecs.winnersList.map(w => w.toBase58()), challengeId };
         this.cacheIdempotencyResult(scope, result);
@@ -1026,9 +1097,9 @@ export class SolanaService {
 
   // Refund for specific challenge 
   async refundForChallenge(challengeId: string) {
-    // Derive PDA for (wrapper owner, challengeId) 
-    const owner = this.wallet.publicKey;
-    const pda = this.statePdaFor(owner, challengeId);
+    // Derive PDA for (wrapper creator, challengeId) 
+    const creator = this.wallet.publicKey;
+    const pda = this.statePdaFor(creator, challengeId);
 
     // Read subscribers from on-chain state
     const data = await this.getAccountData(pda);
@@ -1039,7 +1110,7 @@ export class SolanaService {
 
     // Per-tx accounts & balance guards (same as refundBatch)
     const MAX_TX_ACCOUNTS = 64;
-    const BASE_KEYS = 3;
+    const BASE_KEYS = 4;
     if (subs.length + BASE_KEYS > MAX_TX_ACCOUNTS) {
       this.fail(422, 'TOO_MANY_SUBSCRIBERS_FOR_SINGLE_TX', `Max ~${MAX_TX_ACCOUNTS - BASE_KEYS} subscribers per tx`);
     }
@@ -1056,6 +1127,7 @@ export class SolanaService {
       { pubkey: pda, isSigner: false, isWritable: true },
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
       { pubkey: SystemProgram.programId, isSigner: false, isWritable: false },
+      { pubkey: hdr.treasury, isSigner: false, isWritable: true },          // refund_fee goes here
     ];
     const remaining = subs.map(s => ({ pubkey: s, isSigner: false, isWritable: true } as AccountMeta));
 
@@ -1065,9 +1137,9 @@ export class SolanaService {
   }
 
   // Set owner for specific challenge
-  async setOwnerForChallenge(challengeId: string, params: { newOwner: string; owner?: string }) {
-    const owner = this.pickOwner(params.owner);
-    const pda = this.statePdaFor(owner, challengeId);
+  async setOwnerForChallenge(challengeId: string, params: { newOwner: string; creator?: string }) {
+    const creator = this.pickCreator(params.creator);
+    const pda = this.statePdaFor(creator, challengeId);
     const keys: AccountMeta[] = [
       { pubkey: pda, isSigner: false, isWritable: true },
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
@@ -1078,9 +1150,9 @@ export class SolanaService {
   }
 
   // Remove owner for specific challenge
-  async removeOwnerForChallenge(challengeId: string, params: { user: string; owner?: string }) {
-    const owner = this.pickOwner(params.owner);
-    const pda = this.statePdaFor(owner, challengeId);
+  async removeOwnerForChallenge(challengeId: string, params: { user: string; creator?: string }) {
+    const creator = this.pickCreator(params.creator);
+    const pda = this.statePdaFor(creator, challengeId);
     const keys: AccountMeta[] = [
       { pubkey: pda, isSigner: false, isWritable: true },
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
@@ -1091,9 +1163,9 @@ export class SolanaService {
   }
 
   // Cancel subscription for specific challenge
-  async cancelSubscriptionForChallenge(challengeId: string, params: { subscriber: string; owner?: string }) {
-    const owner = this.pickOwner(params.owner);
-    const pda = this.statePdaFor(owner, challengeId);
+  async cancelSubscriptionForChallenge(challengeId: string, params: { subscriber: string; creator?: string }) {
+    const creator = this.pickCreator(params.creator);
+    const pda = this.statePdaFor(creator, challengeId);
     const subscriber = toPubkey(params.subscriber);
     const keys: AccountMeta[] = [
       { pubkey: pda, isSigner: false, isWritable: true },
@@ -1105,14 +1177,16 @@ export class SolanaService {
   }
 
   // Set treasury for specific challenge
-  async setTreasuryForChallenge(challengeId: string, params: { newTreasury: string; owner?: string }) {
-    const owner = this.pickOwner(params.owner);
-    const pda = this.statePdaFor(owner, challengeId);
+  async setTreasuryForChallenge(challengeId: string, params: { newTreasury: string; creator?: string }) {
+    const creator = this.pickCreator(params.creator);
+    const pda = this.statePdaFor(creator, challengeId);
+    const newTreasury = toPubkey(params.newTreasury);
     const keys: AccountMeta[] = [
       { pubkey: pda, isSigner: false, isWritable: true },
       { pubkey: this.wallet.publicKey, isSigner: true, isWritable: false },
+      { pubkey: newTreasury, isSigner: false, isWritable: false },           // treasury_wallet (owner check)
     ];
-    const signature = await this.sendIx('set_treasury', keys, { newTreasury: toPubkey(params.newTreasury) });
+    const signature = await this.sendIx('set_treasury', keys, { newTreasury });
     this.cache.del('state:' + pda.toBase58());
     return { signature, challengeId };
   }
@@ -1139,8 +1213,8 @@ export class SolanaService {
   // Check if state exists for challenge - no env mutation
   async checkStateForChallenge(challengeId: string): Promise<boolean> {
     try {
-      const owner = this.wallet.publicKey;
-      const pda = this.statePdaFor(owner, challengeId);
+      const creator = this.wallet.publicKey;
+      const pda = this.statePdaFor(creator, challengeId);
       const accountInfo = await this.connection.getAccountInfo(pda);
       return accountInfo !== null;
     } catch {